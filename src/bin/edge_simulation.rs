@@ -4,7 +4,7 @@ use rand::{Rng, SeedableRng};
 use rand::rngs::SmallRng;
 use std::time::Instant;
 use computational_fundamentals::{
-    resource_aware::{Budgets, ResourceAwareScheduler, Task},
+    resource_aware::{Budgets, Resource, ResourceAwareScheduler, Task},
     uncertainty_quantification::UncertainValue,
     self_modifying::SelfOptimizingCache,
     algebraic_composability::{TaskStats, task_stats_monoid},
@@ -66,13 +66,22 @@ impl EdgeServer {
             });
 
             self.cache.put(task.name.clone(), format!("result_{}", outcome));
+        } else {
+            let decision = self.scheduler.explain(task, risk_tolerance);
+            if let (Some(resource), Some(check)) = (&decision.binding, decision.binding_check()) {
+                println!("{} {}: {} overload risk {:.1}%, headroom {:.2}", "Rejected".red(), task.name, resource, check.overload_probability * 100.0, check.headroom);
+            }
         }
     }
 }
 
 fn main() -> Result<()> {
     println!("{}", "🚀 Starting Full Edge Server Simulation".bold().yellow());
-    let budgets = Budgets { cpu: 1000.0, energy: 10000.0, memory: 1e11, bandwidth: 1e10 };
+    let budgets = Budgets::new()
+        .with(Resource::CPU, 1000.0)
+        .with(Resource::ENERGY, 10000.0)
+        .with(Resource::MEMORY, 1e11)
+        .with(Resource::BANDWIDTH, 1e10);
     let mut server = EdgeServer::new(budgets);
 
     let start_time = Instant::now();
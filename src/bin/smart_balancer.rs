@@ -3,7 +3,6 @@ use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::Mutex;
-use tokio::time::timeout;
 use hyper::body::{Bytes, Incoming};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
@@ -12,23 +11,25 @@ use http_body_util::Full;
 use hyper_util::rt::TokioIo;
 use std::sync::Arc;
 use computational_fundamentals::{
-    resource_aware::{Budgets, ResourceAwareScheduler, Task},
+    resource_aware::{Budgets, Resource, ResourceAwareScheduler, Task},
+    resource_aware::cluster::{Cluster, PlacementPolicy},
     self_modifying::SelfOptimizingCache,
     adversarial_first::SecureHashMap,
     uncertainty_quantification::UncertainValue,
     algebraic_composability::{TaskStats, task_stats_monoid},
+    time_aware::deadline::Deadline,
 };
 
 const RATE_LIMIT_THRESHOLD: u64 = 100;
-const BACKEND_TIMEOUT_MS: u64 = 50;
-
-struct BackendServer {
-    addr: SocketAddr,
-    scheduler: ResourceAwareScheduler,
-}
+const REQUEST_BUDGET_MS: u64 = 70;
+// Held back from the backend call for replying, leaving it the 50 ms it had
+// before deadlines were propagated.
+const RESPONSE_RESERVE_MS: u64 = 20;
 
 struct LoadBalancer {
-    backends: Vec<BackendServer>,
+    /// Backend addresses, indexed like the nodes of `cluster`.
+    backends: Vec<SocketAddr>,
+    cluster: Cluster,
     rate_limiter: SecureHashMap,
     cache: SelfOptimizingCache<String, String>,
     stats: TaskStats,
@@ -65,29 +66,35 @@ async fn handle_request(req: Request<Incoming>, balancer: Arc<Mutex<LoadBalancer
         value: 10.0,
     };
 
-    for backend in &mut balancer_guard.backends {
-        if backend.scheduler.schedule_task(&task, 0.1) {
-            println!("Forwarding to backend {}", backend.addr);
-
-            let backend_response = timeout(Duration::from_millis(BACKEND_TIMEOUT_MS), async {
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                Ok::<_, hyper::Error>(format!("Response for {} from backend {}", path, backend.addr))
-            }).await;
-
-            return match backend_response {
-                Ok(Ok(body)) => {
-                    balancer_guard.cache.put(path, body.clone());
-                    balancer_guard.stats = (task_stats_monoid().operation)(balancer_guard.stats.clone(), TaskStats { tasks_processed: 1, data_processed: task.data_size });
-                    Ok(Response::new(Full::new(Bytes::from(body))))
-                },
-                _ => {
-                    println!("Backend {} timed out", backend.addr);
-                    let mut resp = Response::new(Full::new(Bytes::from("Gateway Timeout")));
-                    *resp.status_mut() = StatusCode::GATEWAY_TIMEOUT;
-                    Ok(resp)
-                }
-            };
-        }
+    let (decision, reservation) = balancer_guard.cluster.place(&task, 0.1);
+    println!("{}", decision.reason);
+    if let (Some(node), Some(reservation)) = (decision.node, reservation) {
+        let backend = balancer_guard.backends[node];
+        println!("Forwarding to backend {}", backend);
+
+        let deadline = Deadline::current().unwrap_or_else(|| Deadline::after(Duration::from_millis(REQUEST_BUDGET_MS)));
+        let backend_response = deadline.reserve(Duration::from_millis(RESPONSE_RESERVE_MS)).run(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok::<_, hyper::Error>(format!("Response for {} from backend {}", path, backend))
+        }).await;
+
+        return match backend_response {
+            Ok(Ok(body)) => {
+                // Nothing is metered on the backend, so settle at the estimate.
+                let estimated = reservation.estimated().clone();
+                reservation.complete(&estimated);
+                balancer_guard.cache.put(path, body.clone());
+                balancer_guard.stats = (task_stats_monoid().operation)(balancer_guard.stats.clone(), TaskStats { tasks_processed: 1, data_processed: task.data_size });
+                Ok(Response::new(Full::new(Bytes::from(body))))
+            },
+            _ => {
+                reservation.cancel();
+                println!("Backend {} timed out", backend);
+                let mut resp = Response::new(Full::new(Bytes::from("Gateway Timeout")));
+                *resp.status_mut() = StatusCode::GATEWAY_TIMEOUT;
+                Ok(resp)
+            }
+        };
     }
 
     let mut resp = Response::new(Full::new(Bytes::from("Service Unavailable")));
@@ -95,24 +102,30 @@ async fn handle_request(req: Request<Incoming>, balancer: Arc<Mutex<LoadBalancer
     Ok(resp)
 }
 
+fn backend_budgets() -> Budgets {
+    Budgets::new()
+        .with(Resource::CPU, 10.0)
+        .with(Resource::ENERGY, 100.0)
+        .with(Resource::MEMORY, 1e9)
+        .with(Resource::BANDWIDTH, 1e8)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     let listener = TcpListener::bind(addr).await?;
 
     let backends = vec![
-        BackendServer {
-            addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
-            scheduler: ResourceAwareScheduler::new(Budgets { cpu: 10.0, energy: 100.0, memory: 1e9, bandwidth: 1e8 }),
-        },
-        BackendServer {
-            addr: SocketAddr::from(([127, 0, 0, 1], 8081)),
-            scheduler: ResourceAwareScheduler::new(Budgets { cpu: 10.0, energy: 100.0, memory: 1e9, bandwidth: 1e8 }),
-        },
+        SocketAddr::from(([127, 0, 0, 1], 8080)),
+        SocketAddr::from(([127, 0, 0, 1], 8081)),
     ];
+    let cluster = backends.iter().fold(Cluster::new(PlacementPolicy::LowestOverloadRisk), |cluster, backend| {
+        cluster.with_node(&backend.to_string(), ResourceAwareScheduler::new(backend_budgets()))
+    });
 
     let balancer = Arc::new(Mutex::new(LoadBalancer {
         backends,
+        cluster,
         rate_limiter: SecureHashMap::new(),
         cache: SelfOptimizingCache::new(100),
         stats: task_stats_monoid().identity(),
@@ -127,7 +140,9 @@ async fn main() -> Result<()> {
         let balancer = balancer.clone();
         tokio::task::spawn(async move {
             if let Err(err) = http1::Builder::new()
-                .serve_connection(io, service_fn(move |req| handle_request(req, balancer.clone())))
+                .serve_connection(io, service_fn(move |req| {
+                    Deadline::after(Duration::from_millis(REQUEST_BUDGET_MS)).scope(handle_request(req, balancer.clone()))
+                }))
                 .await
             {
                 eprintln!("Error serving connection: {:?}", err);
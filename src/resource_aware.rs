@@ -1,5 +1,28 @@
+pub mod accounting;
+pub mod cluster;
+pub mod cost;
+pub mod decision;
+pub mod dvfs;
+pub mod planner;
+pub mod resource;
+pub mod risk;
+pub mod tenancy;
+pub mod workflow;
+
+pub use resource::{Budgets, Resource, ResourceVector};
+
+use crate::time_aware::clock::{Clock, MonotonicClock};
 use crate::uncertainty_quantification::UncertainValue;
-use std::collections::HashMap;
+use accounting::{lock, AccountingSummary, Ledger, TaskHandle};
+use cost::{CostEstimate, CostModel, LinearCostModel};
+use decision::ScheduleDecision;
+use planner::{BatchPlan, PlanningStrategy};
+use risk::{OverloadRisk, RiskMode};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tenancy::{Quota, TenantDecision};
+use workflow::{Workflow, WorkflowAdmission, WorkflowPlan};
 
 /// Represents a computational task with various resource requirements.
 pub struct Task {
@@ -15,103 +38,301 @@ pub struct Task {
     pub value: f64,
 }
 
-/// Defines the resource budgets for the scheduler.
-pub struct Budgets {
-    /// The CPU budget.
-    pub cpu: f64,
-    /// The energy budget.
-    pub energy: f64,
-    /// The memory budget.
-    pub memory: f64,
-    /// The bandwidth budget.
-    pub bandwidth: f64,
-}
-
 /// A scheduler that makes decisions based on resource availability and task requirements.
-pub struct ResourceAwareScheduler {
+///
+/// Task costs come from a [`CostModel`]; `new` uses the default
+/// [`LinearCostModel`], and `with_cost_model` accepts any other. Admitted
+/// tasks hold their expected cost until their [`TaskHandle`] is completed or
+/// cancelled. How the risks of individual budgets combine is set by a
+/// [`RiskMode`]. Budgets can be shared among tenants with a [`Quota`] each.
+pub struct ResourceAwareScheduler<M = LinearCostModel> {
     budgets: Budgets,
-    consumed: HashMap<String, f64>,
+    ledger: Arc<Mutex<Ledger>>,
+    cost_model: M,
+    risk_mode: RiskMode,
+    tenants: BTreeMap<String, Quota>,
 }
 
 impl ResourceAwareScheduler {
     /// Creates a new `ResourceAwareScheduler` with the given budgets.
     pub fn new(budgets: Budgets) -> Self {
-        let mut consumed = HashMap::new();
-        consumed.insert("cpu".to_string(), 0.0);
-        consumed.insert("energy".to_string(), 0.0);
-        consumed.insert("memory".to_string(), 0.0);
-        consumed.insert("bandwidth".to_string(), 0.0);
-        ResourceAwareScheduler { budgets, consumed }
-    }
-
-    fn estimate_cost(&self, task: &Task) -> HashMap<String, UncertainValue> {
-        let mut cost = HashMap::new();
-        cost.insert(
-            "cpu".to_string(),
-            UncertainValue::new(task.operations.mean / 1e9, task.operations.std_dev / 1e9),
-        );
-        cost.insert(
-            "energy".to_string(),
-            UncertainValue::new(task.operations.mean * 1e-9, task.operations.std_dev * 1e-9),
-        );
-        cost.insert(
-            "memory".to_string(),
-            UncertainValue::new(task.data_size, 0.0),
-        );
-        cost.insert(
-            "bandwidth".to_string(),
-            if task.network {
-                UncertainValue::new(task.data_size, 0.0)
-            } else {
-                UncertainValue::new(0.0, 0.0)
-            },
-        );
-        cost
+        Self::with_cost_model(budgets, LinearCostModel::new())
+    }
+}
+
+impl<M: CostModel> ResourceAwareScheduler<M> {
+    /// Creates a new `ResourceAwareScheduler` that estimates task costs with `cost_model`.
+    pub fn with_cost_model(budgets: Budgets, cost_model: M) -> Self {
+        let ledger = Ledger::new(&budgets, Box::new(MonotonicClock::new()));
+        ResourceAwareScheduler {
+            budgets,
+            ledger: Arc::new(Mutex::new(ledger)),
+            cost_model,
+            risk_mode: RiskMode::default(),
+            tenants: BTreeMap::new(),
+        }
+    }
+
+    /// Measures the refill windows of windowed budgets with `clock`.
+    pub fn with_clock<C: Clock + Send + Sync + 'static>(self, clock: C) -> Self {
+        lock(&self.ledger).set_clock(Box::new(clock));
+        self
+    }
+
+    /// Compares `risk_mode`'s combination of the per-budget overload
+    /// probabilities against the risk tolerance.
+    pub fn with_risk_mode(mut self, risk_mode: RiskMode) -> Self {
+        self.risk_mode = risk_mode;
+        self
+    }
+
+    /// Returns how the risks of individual budgets are combined.
+    pub fn risk_mode(&self) -> RiskMode {
+        self.risk_mode
+    }
+
+    /// Shares the budgets with `tenant` under `quota`, replacing any quota
+    /// it had.
+    pub fn with_tenant(mut self, tenant: &str, quota: Quota) -> Self {
+        self.tenants.insert(tenant.to_string(), quota);
+        self
+    }
+
+    /// Returns the quota of `tenant`, if it has one.
+    pub fn quota(&self, tenant: &str) -> Option<&Quota> {
+        self.tenants.get(tenant)
+    }
+
+    /// Returns the budgets the scheduler enforces.
+    pub fn budgets(&self) -> &Budgets {
+        &self.budgets
+    }
+
+    /// Returns the resources currently committed: the expected cost of
+    /// running tasks, plus what finished tasks used of windowed budgets
+    /// within their current window.
+    pub fn consumed(&self) -> ResourceVector {
+        lock(&self.ledger).committed_all()
+    }
+
+    /// Returns totals over the tasks admitted so far, including how their
+    /// actual usage compared to the estimates.
+    pub fn accounting(&self) -> AccountingSummary {
+        lock(&self.ledger).summary()
+    }
+
+    /// Returns the cost model used to estimate tasks.
+    pub fn cost_model(&self) -> &M {
+        &self.cost_model
+    }
+
+    /// Reports the resources a task actually consumed, so that the cost
+    /// model can calibrate itself.
+    pub fn record_execution(&mut self, task: &Task, actual: &ResourceVector) {
+        self.cost_model.observe(task, actual);
     }
 
     /// Determines if a task can be scheduled without exceeding the resource budgets,
     /// given a certain risk tolerance.
     pub fn can_schedule(&self, task: &Task, risk_tolerance: f64) -> bool {
-        let cost = self.estimate_cost(task);
+        let cost = self.cost_model.estimate(task);
         self.can_schedule_with_cost(&cost, risk_tolerance)
     }
 
-    fn can_schedule_with_cost(
+    /// Returns the risk that admitting `task` now overloads the budgets, and
+    /// which resource dominates it.
+    pub fn overload_risk(&self, task: &Task) -> OverloadRisk {
+        self.overload_risk_with_cost(&self.cost_model.estimate(task))
+    }
+
+    fn overload_risk_with_cost(&self, cost: &CostEstimate) -> OverloadRisk {
+        risk::assess(cost, &self.remaining_for(cost), self.risk_mode)
+    }
+
+    /// Returns what is left of each budget `cost` draws on. Resources
+    /// without a budget are unconstrained and left out.
+    fn remaining_for(&self, cost: &CostEstimate) -> ResourceVector {
+        self.remaining_of(cost.iter().map(|(resource, _)| resource))
+    }
+
+    /// Returns what is left of each budgeted resource among `resources`:
+    /// the limit less what is committed and less every tenant's unused
+    /// guarantee, which untenanted tasks may not take.
+    fn remaining_of<'a>(&self, resources: impl Iterator<Item = &'a Resource>) -> ResourceVector {
+        let mut ledger = lock(&self.ledger);
+        resources
+            .filter_map(|resource| {
+                let budget = self.budgets.limit(resource)?;
+                let protected = tenancy::protected(self, &mut ledger, resource, None);
+                Some((resource.clone(), budget - ledger.committed(resource) - protected))
+            })
+            .collect()
+    }
+
+    fn can_schedule_with_cost(&self, cost: &CostEstimate, risk_tolerance: f64) -> bool {
+        self.overload_risk_with_cost(cost).within(risk_tolerance)
+    }
+
+    /// Explains whether `task` would be admitted now: the overload
+    /// probability and headroom of each budget, and which one binds.
+    pub fn explain(&self, task: &Task, risk_tolerance: f64) -> ScheduleDecision {
+        self.explain_with_cost(task, &self.cost_model.estimate(task), risk_tolerance)
+    }
+
+    fn explain_with_cost(
         &self,
-        cost: &HashMap<String, UncertainValue>,
+        task: &Task,
+        cost: &CostEstimate,
+        risk_tolerance: f64,
+    ) -> ScheduleDecision {
+        let remaining = self.remaining_for(cost);
+        let risk = risk::assess(cost, &remaining, self.risk_mode);
+        ScheduleDecision::new(&task.name, &self.budgets, cost, &remaining, risk, risk_tolerance)
+    }
+
+    fn reserve(&self, task: &Task, cost: &CostEstimate, tenant: Option<&str>) -> TaskHandle {
+        let estimated = cost.mean(); // Use the mean for accounting
+        lock(&self.ledger).reserve(&estimated, tenant);
+        TaskHandle::new(&task.name, tenant, estimated, self.ledger.clone())
+    }
+
+    /// Admits a task if it can be accommodated within the resource budgets,
+    /// reserving its expected cost until the returned handle is completed or
+    /// cancelled. Returns `None` if the task was rejected.
+    pub fn schedule(&mut self, task: &Task, risk_tolerance: f64) -> Option<TaskHandle> {
+        let cost = self.cost_model.estimate(task);
+        self.can_schedule_with_cost(&cost, risk_tolerance)
+            .then(|| self.reserve(task, &cost, None))
+    }
+
+    /// Admits a task like [`schedule`](Self::schedule), also returning the
+    /// [`ScheduleDecision`] that explains the outcome.
+    pub fn schedule_explained(
+        &mut self,
+        task: &Task,
         risk_tolerance: f64,
-    ) -> bool {
-        let cpu_budget = self.budgets.cpu;
-        let energy_budget = self.budgets.energy;
-        let memory_budget = self.budgets.memory;
-        let bandwidth_budget = self.budgets.bandwidth;
-
-        let cpu_overload_prob = 1.0 - cost["cpu"].confidence(cpu_budget - self.consumed["cpu"]);
-        let energy_overload_prob =
-            1.0 - cost["energy"].confidence(energy_budget - self.consumed["energy"]);
-        let memory_overload_prob =
-            1.0 - cost["memory"].confidence(memory_budget - self.consumed["memory"]);
-        let bandwidth_overload_prob =
-            1.0 - cost["bandwidth"].confidence(bandwidth_budget - self.consumed["bandwidth"]);
-
-        cpu_overload_prob < risk_tolerance
-            && energy_overload_prob < risk_tolerance
-            && memory_overload_prob < risk_tolerance
-            && bandwidth_overload_prob < risk_tolerance
+    ) -> (ScheduleDecision, Option<TaskHandle>) {
+        let cost = self.cost_model.estimate(task);
+        let decision = self.explain_with_cost(task, &cost, risk_tolerance);
+        let handle = decision.admitted.then(|| self.reserve(task, &cost, None));
+        (decision, handle)
     }
 
     /// Schedules a task if it can be accommodated within the resource budgets.
     /// Returns `true` if the task was scheduled, `false` otherwise.
+    ///
+    /// The task's reservation is never released; use [`schedule`](Self::schedule)
+    /// for tasks that finish, and [`explain`](Self::explain) to find out why a
+    /// task was rejected.
     pub fn schedule_task(&mut self, task: &Task, risk_tolerance: f64) -> bool {
-        let cost = self.estimate_cost(task);
-        if self.can_schedule_with_cost(&cost, risk_tolerance) {
-            for (resource, value) in cost {
-                *self.consumed.get_mut(&resource).unwrap() += value.mean; // Use the mean for accounting
-            }
-            true
-        } else {
-            false
-        }
+        self.schedule(task, risk_tolerance)
+            .map(TaskHandle::detach)
+            .is_some()
+    }
+
+    /// Selects the subset of `tasks` with the greatest total value whose
+    /// combined cost fits every budget with an overload probability below
+    /// `risk_tolerance`. Nothing is reserved.
+    ///
+    /// Combined costs are approximated as normal, and `MonteCarlo` risk is
+    /// checked with the union bound, so the plan can differ from what
+    /// [`schedule`](Self::schedule) would admit one task at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `strategy` is `Exact` and there are more than
+    /// [`planner::MAX_EXACT_CANDIDATES`] tasks.
+    pub fn plan_batch(
+        &self,
+        tasks: &[Task],
+        risk_tolerance: f64,
+        strategy: PlanningStrategy,
+    ) -> BatchPlan {
+        planner::plan(self, tasks, risk_tolerance, strategy)
+    }
+
+    /// Plans a batch like [`plan_batch`](Self::plan_batch) and admits the
+    /// selected tasks, returning their handles in the order of `plan.selected`.
+    pub fn schedule_batch(
+        &mut self,
+        tasks: &[Task],
+        risk_tolerance: f64,
+        strategy: PlanningStrategy,
+    ) -> (BatchPlan, Vec<TaskHandle>) {
+        let plan = self.plan_batch(tasks, risk_tolerance, strategy);
+        let handles = plan
+            .selected
+            .iter()
+            .map(|&i| self.reserve(&tasks[i], &self.cost_model.estimate(&tasks[i]), None))
+            .collect();
+        (plan, handles)
+    }
+
+    /// Returns the guarantee plus weighted fair share of every budget that
+    /// `tenant` is entitled to. Tenants without a quota are entitled to
+    /// nothing.
+    pub fn entitlement(&self, tenant: &str) -> ResourceVector {
+        tenancy::entitlement(self, tenant)
+    }
+
+    /// Returns what is committed to `tenant`: the expected cost of its
+    /// running tasks plus, for windowed budgets, what its completed tasks
+    /// used within the current window.
+    pub fn tenant_usage(&self, tenant: &str) -> ResourceVector {
+        lock(&self.ledger).tenant_committed_all(tenant)
+    }
+
+    /// Explains whether `task` would be admitted for `tenant` now, given
+    /// the tenant's quota and what the other tenants hold.
+    pub fn explain_for(&self, tenant: &str, task: &Task, risk_tolerance: f64) -> TenantDecision {
+        let cost = self.cost_model.estimate(task);
+        tenancy::explain(self, tenant, task, &cost, risk_tolerance)
+    }
+
+    /// Admits a task on behalf of `tenant` if it fits both the budgets and
+    /// the tenant's share of them. The reservation counts against the
+    /// tenant until the returned handle is completed or cancelled.
+    pub fn schedule_for(
+        &mut self,
+        tenant: &str,
+        task: &Task,
+        risk_tolerance: f64,
+    ) -> (TenantDecision, Option<TaskHandle>) {
+        let cost = self.cost_model.estimate(task);
+        let decision = tenancy::explain(self, tenant, task, &cost, risk_tolerance);
+        let handle = decision
+            .decision
+            .admitted
+            .then(|| self.reserve(task, &cost, Some(tenant)));
+        (decision, handle)
+    }
+
+    /// Admits every task of `workflow`, or none, if its critical path
+    /// finishes within `deadline` with probability at least `confidence` and
+    /// each task fits the budgets with `risk_tolerance`. Tasks are admitted
+    /// in dependency order and all hold their reservations at once.
+    pub fn schedule_workflow(
+        &mut self,
+        workflow: &Workflow,
+        deadline: Duration,
+        confidence: f64,
+        risk_tolerance: f64,
+    ) -> (WorkflowPlan, WorkflowAdmission) {
+        workflow::schedule(self, None, workflow, deadline, confidence, risk_tolerance)
+    }
+
+    /// Admits a workflow like [`schedule_workflow`](Self::schedule_workflow)
+    /// on behalf of `tenant`: each task must also fit the tenant's share of
+    /// the budgets, and every reservation counts against the tenant.
+    pub fn schedule_workflow_for(
+        &mut self,
+        tenant: &str,
+        workflow: &Workflow,
+        deadline: Duration,
+        confidence: f64,
+        risk_tolerance: f64,
+    ) -> (WorkflowPlan, WorkflowAdmission) {
+        workflow::schedule(self, Some(tenant), workflow, deadline, confidence, risk_tolerance)
     }
 }
 
@@ -121,12 +342,11 @@ mod tests {
 
     #[test]
     fn test_verify_resource_optimization() {
-        let budgets = Budgets {
-            cpu: 10.0,
-            energy: 100.0,
-            memory: 1_000_000_000.0,
-            bandwidth: 100_000_000.0,
-        };
+        let budgets = Budgets::new()
+            .with(Resource::CPU, 10.0)
+            .with(Resource::ENERGY, 100.0)
+            .with(Resource::MEMORY, 1_000_000_000.0)
+            .with(Resource::BANDWIDTH, 100_000_000.0);
         let mut scheduler = ResourceAwareScheduler::new(budgets);
 
         let huge_task = Task {
@@ -0,0 +1,369 @@
+use super::{Budgets, Resource, ResourceVector};
+use crate::time_aware::clock::Clock;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The bookkeeping shared between a scheduler and its outstanding handles.
+pub(crate) struct Ledger {
+    clock: Box<dyn Clock + Send + Sync>,
+    windows: BTreeMap<Resource, Duration>,
+    /// Estimated cost of every task that has been admitted but not settled.
+    reserved: ResourceVector,
+    /// The part of `reserved` held by each tenant's tasks.
+    tenants: BTreeMap<String, ResourceVector>,
+    /// Actual usage of windowed resources, by completion time.
+    charges: BTreeMap<Resource, VecDeque<Charge>>,
+    summary: AccountingSummary,
+}
+
+/// The actual usage of a windowed resource by one completed task.
+struct Charge {
+    at: Duration,
+    amount: f64,
+    tenant: Option<String>,
+}
+
+impl Ledger {
+    pub(crate) fn new(budgets: &Budgets, clock: Box<dyn Clock + Send + Sync>) -> Self {
+        Ledger {
+            clock,
+            windows: budgets.windows().clone(),
+            reserved: ResourceVector::new(),
+            tenants: BTreeMap::new(),
+            charges: BTreeMap::new(),
+            summary: AccountingSummary::default(),
+        }
+    }
+
+    /// Returns how much of `resource` is spoken for: reservations of running
+    /// tasks plus, for windowed resources, what completed tasks used within
+    /// the current window.
+    pub(crate) fn committed(&mut self, resource: &Resource) -> f64 {
+        self.reserved.get(resource) + self.window_usage(resource)
+    }
+
+    /// Returns the committed amount of every resource that has any.
+    pub(crate) fn committed_all(&mut self) -> ResourceVector {
+        let resources: Vec<Resource> = self
+            .reserved
+            .iter()
+            .map(|(r, _)| r.clone())
+            .chain(self.charges.keys().cloned())
+            .collect();
+        resources
+            .into_iter()
+            .map(|r| {
+                let amount = self.committed(&r);
+                (r, amount)
+            })
+            .collect()
+    }
+
+    fn window_usage(&mut self, resource: &Resource) -> f64 {
+        self.window_charges(resource).map(|c| c.amount).sum()
+    }
+
+    /// Returns the charges of `resource` within the current window,
+    /// dropping the ones that have expired.
+    fn window_charges(&mut self, resource: &Resource) -> impl Iterator<Item = &Charge> {
+        let now = self.clock.now();
+        let charges = match (self.windows.get(resource), self.charges.get_mut(resource)) {
+            (Some(window), Some(charges)) => {
+                while charges
+                    .front()
+                    .is_some_and(|c| now.saturating_sub(c.at) >= *window)
+                {
+                    charges.pop_front();
+                }
+                Some(&*charges)
+            }
+            _ => None,
+        };
+        charges.into_iter().flatten()
+    }
+
+    pub(crate) fn reserve(&mut self, estimated: &ResourceVector, tenant: Option<&str>) {
+        self.reserved += estimated;
+        if let Some(tenant) = tenant {
+            *self.tenants.entry(tenant.to_string()).or_default() += estimated;
+        }
+        self.summary.in_flight += 1;
+    }
+
+    /// Returns how much of `resource` is spoken for by `tenant`: the
+    /// reservations of its running tasks plus, for windowed resources, what
+    /// its completed tasks used within the current window.
+    pub(crate) fn tenant_committed(&mut self, tenant: &str, resource: &Resource) -> f64 {
+        let reserved = self.tenants.get(tenant).map_or(0.0, |r| r.get(resource));
+        let charged: f64 = self
+            .window_charges(resource)
+            .filter(|c| c.tenant.as_deref() == Some(tenant))
+            .map(|c| c.amount)
+            .sum();
+        reserved + charged
+    }
+
+    /// Returns the amount committed to `tenant` of every resource it has
+    /// used.
+    pub(crate) fn tenant_committed_all(&mut self, tenant: &str) -> ResourceVector {
+        let resources: Vec<Resource> = self
+            .tenants
+            .get(tenant)
+            .into_iter()
+            .flat_map(|reserved| reserved.iter().map(|(r, _)| r.clone()))
+            .chain(self.charges.keys().cloned())
+            .collect();
+        resources
+            .into_iter()
+            .map(|r| {
+                let amount = self.tenant_committed(tenant, &r);
+                (r, amount)
+            })
+            .collect()
+    }
+
+    /// Releases a reservation and, if the task ran, charges its actual usage
+    /// against the windowed budgets and, within them, against its tenant.
+    fn settle(
+        &mut self,
+        estimated: &ResourceVector,
+        tenant: Option<&str>,
+        actual: Option<&ResourceVector>,
+    ) {
+        self.reserved -= estimated;
+        if let Some(reserved) = tenant.and_then(|tenant| self.tenants.get_mut(tenant)) {
+            *reserved -= estimated;
+        }
+        self.summary.in_flight -= 1;
+        let Some(actual) = actual else {
+            self.summary.cancelled += 1;
+            return;
+        };
+        self.summary.completed += 1;
+        self.summary.estimated += estimated;
+        self.summary.actual += actual;
+        let now = self.clock.now();
+        for (resource, amount) in actual.iter() {
+            if self.windows.contains_key(resource) {
+                self.charges
+                    .entry(resource.clone())
+                    .or_default()
+                    .push_back(Charge {
+                        at: now,
+                        amount,
+                        tenant: tenant.map(str::to_string),
+                    });
+            }
+        }
+    }
+
+    pub(crate) fn set_clock(&mut self, clock: Box<dyn Clock + Send + Sync>) {
+        self.clock = clock;
+    }
+
+    pub(crate) fn summary(&self) -> AccountingSummary {
+        self.summary.clone()
+    }
+}
+
+pub(crate) fn lock(ledger: &Mutex<Ledger>) -> MutexGuard<'_, Ledger> {
+    // The ledger is only mutated in short, panic-free sections, so its state
+    // stays consistent even if a holder panicked.
+    ledger
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Totals over the tasks a scheduler has admitted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountingSummary {
+    /// Tasks admitted and not yet completed or cancelled.
+    pub in_flight: usize,
+    /// Tasks completed through their handle.
+    pub completed: usize,
+    /// Tasks cancelled, explicitly or by dropping their handle.
+    pub cancelled: usize,
+    /// The summed cost estimates of completed tasks.
+    pub estimated: ResourceVector,
+    /// The summed actual usage of completed tasks.
+    pub actual: ResourceVector,
+}
+
+impl AccountingSummary {
+    /// Returns how much actual usage of `resource` exceeded the estimates,
+    /// as a fraction of the estimates: `0.1` means tasks used 10% more than
+    /// predicted. Returns `None` if nothing of `resource` was estimated.
+    pub fn estimation_bias(&self, resource: &Resource) -> Option<f64> {
+        let estimated = self.estimated.get(resource);
+        (estimated != 0.0).then(|| self.actual.get(resource) / estimated - 1.0)
+    }
+}
+
+/// How a completed task's actual usage compared to its estimate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation {
+    /// The expected cost the task was admitted with.
+    pub estimated: ResourceVector,
+    /// The usage reported on completion.
+    pub actual: ResourceVector,
+}
+
+impl Reconciliation {
+    /// Returns actual minus estimated usage of `resource`.
+    pub fn error(&self, resource: &Resource) -> f64 {
+        self.actual.get(resource) - self.estimated.get(resource)
+    }
+}
+
+/// A reservation held by an admitted task.
+///
+/// The task's expected cost counts against the scheduler's budgets until the
+/// handle is completed or cancelled. Dropping a handle without either cancels
+/// it, so a task that fails or is abandoned cannot leak its reservation.
+#[must_use = "dropping a TaskHandle cancels the reservation"]
+pub struct TaskHandle {
+    name: String,
+    tenant: Option<String>,
+    estimated: ResourceVector,
+    ledger: Arc<Mutex<Ledger>>,
+    settled: bool,
+}
+
+impl TaskHandle {
+    pub(crate) fn new(
+        name: &str,
+        tenant: Option<&str>,
+        estimated: ResourceVector,
+        ledger: Arc<Mutex<Ledger>>,
+    ) -> Self {
+        TaskHandle {
+            name: name.to_string(),
+            tenant: tenant.map(str::to_string),
+            estimated,
+            ledger,
+            settled: false,
+        }
+    }
+
+    /// Returns the name of the task.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the tenant the task was admitted for, if any.
+    pub fn tenant(&self) -> Option<&str> {
+        self.tenant.as_deref()
+    }
+
+    /// Returns the expected cost reserved for the task.
+    pub fn estimated(&self) -> &ResourceVector {
+        &self.estimated
+    }
+
+    /// Marks the task as finished, releasing its reservation and charging
+    /// `actual` usage to any windowed budgets.
+    pub fn complete(mut self, actual: &ResourceVector) -> Reconciliation {
+        lock(&self.ledger).settle(&self.estimated, self.tenant.as_deref(), Some(actual));
+        self.settled = true;
+        Reconciliation {
+            estimated: std::mem::take(&mut self.estimated),
+            actual: actual.clone(),
+        }
+    }
+
+    /// Releases the reservation of a task that will not run.
+    pub fn cancel(mut self) {
+        lock(&self.ledger).settle(&self.estimated, self.tenant.as_deref(), None);
+        self.settled = true;
+    }
+
+    /// Keeps the reservation for the lifetime of the scheduler, as for a
+    /// task whose cost is never given back.
+    pub(crate) fn detach(mut self) {
+        lock(&self.ledger).summary.in_flight -= 1;
+        self.settled = true;
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        if !self.settled {
+            lock(&self.ledger).settle(&self.estimated, self.tenant.as_deref(), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::{ResourceAwareScheduler, Task};
+    use crate::time_aware::clock::VirtualClock;
+    use crate::uncertainty_quantification::UncertainValue;
+
+    #[test]
+    fn test_verify_reservation_release() {
+        // Each task is expected to need 4s of CPU and 4J of energy.
+        let task = Task {
+            name: "encode".to_string(),
+            operations: UncertainValue::new(4e9, 1e8),
+            data_size: 0.0,
+            network: false,
+            value: 1.0,
+        };
+        let clock = Arc::new(VirtualClock::new());
+        let budgets = Budgets::new().with(Resource::CPU, 10.0).with_window(
+            Resource::ENERGY,
+            16.0,
+            Duration::from_secs(3600),
+        );
+        let mut scheduler = ResourceAwareScheduler::new(budgets).with_clock(clock.clone());
+
+        let first = scheduler.schedule(&task, 0.05).unwrap();
+        let second = scheduler.schedule(&task, 0.05).unwrap();
+        assert!(
+            scheduler.schedule(&task, 0.05).is_none(),
+            "Only two fit in 10s of CPU"
+        );
+        assert_eq!(scheduler.accounting().in_flight, 2);
+
+        // Completing releases CPU but charges the energy actually used.
+        let actual = ResourceVector::new()
+            .with(Resource::CPU, 5.0)
+            .with(Resource::ENERGY, 6.0);
+        let reconciliation = first.complete(&actual);
+        assert!((reconciliation.error(&Resource::CPU) - 1.0).abs() < 1e-9);
+        second.cancel();
+        assert!(scheduler.consumed().get(&Resource::CPU).abs() < 1e-9);
+        assert!((scheduler.consumed().get(&Resource::ENERGY) - 6.0).abs() < 1e-9);
+
+        // Energy is the binding budget now: with 6J of the hourly 16J used,
+        // two more 4J tasks fit this hour but a third does not, even after the
+        // others finish.
+        for _ in 0..2 {
+            let handle = scheduler.schedule(&task, 0.05).unwrap();
+            handle.complete(&ResourceVector::new().with(Resource::ENERGY, 4.0));
+        }
+        assert!(scheduler.schedule(&task, 0.05).is_none());
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(scheduler.consumed().get(&Resource::ENERGY), 0.0);
+        let refilled = scheduler.schedule(&task, 0.05);
+        assert!(refilled.is_some(), "The energy window has refilled");
+
+        // A dropped handle gives its reservation back.
+        drop(refilled);
+        assert!(scheduler.consumed().get(&Resource::CPU).abs() < 1e-9);
+
+        let summary = scheduler.accounting();
+        assert_eq!(summary.in_flight, 0);
+        assert_eq!(summary.completed, 3);
+        assert_eq!(summary.cancelled, 2);
+        let bias = summary.estimation_bias(&Resource::ENERGY).unwrap();
+        assert!((bias - (14.0 / 12.0 - 1.0)).abs() < 1e-9);
+
+        // The legacy API keeps its reservation for good.
+        assert!(scheduler.schedule_task(&task, 0.05));
+        assert!(scheduler.schedule_task(&task, 0.05));
+        assert!(!scheduler.schedule_task(&task, 0.05));
+    }
+}
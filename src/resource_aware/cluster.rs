@@ -0,0 +1,322 @@
+use super::accounting::TaskHandle;
+use super::cost::{CostModel, LinearCostModel};
+use super::risk::OverloadRisk;
+use super::{ResourceAwareScheduler, Task};
+use std::fmt;
+
+/// How a [`Cluster`] chooses among the nodes that can admit a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementPolicy {
+    /// The first node, in the order they were added.
+    FirstFit,
+    /// The node left with the least headroom, packing nodes tightly and
+    /// keeping others free for large tasks.
+    BestFit,
+    /// The node left with the most headroom, spreading load evenly.
+    WorstFit,
+    /// The node whose dominant share, the largest fraction of any one
+    /// budget in use, is lowest after placement, balancing each node's
+    /// scarcest resource.
+    DominantResourceFairness,
+    /// The node where the task is least likely to cause an overload.
+    LowestOverloadRisk,
+}
+
+impl fmt::Display for PlacementPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlacementPolicy::FirstFit => "first fit",
+            PlacementPolicy::BestFit => "best fit",
+            PlacementPolicy::WorstFit => "worst fit",
+            PlacementPolicy::DominantResourceFairness => "dominant resource fairness",
+            PlacementPolicy::LowestOverloadRisk => "lowest overload risk",
+        })
+    }
+}
+
+/// How one node would fare if it took a task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeAssessment {
+    /// The name of the node.
+    pub node: String,
+    /// The risk that the task overloads the node.
+    pub risk: OverloadRisk,
+    /// Whether the node's scheduler would admit the task.
+    pub admissible: bool,
+    /// The fraction of the node's budgets left free after placement,
+    /// averaged over its budgeted resources.
+    pub headroom: f64,
+    /// The largest fraction of any one budget in use after placement.
+    pub dominant_share: f64,
+}
+
+/// Where a task was placed, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacementDecision {
+    /// The policy that made the decision.
+    pub policy: PlacementPolicy,
+    /// The index of the chosen node, or `None` if no node could admit the task.
+    pub node: Option<usize>,
+    /// How each node would fare, in the order the nodes were added.
+    pub assessments: Vec<NodeAssessment>,
+    /// A human-readable explanation of the decision.
+    pub reason: String,
+}
+
+impl PlacementDecision {
+    /// Returns the name of the chosen node.
+    pub fn node_name(&self) -> Option<&str> {
+        self.node.map(|i| self.assessments[i].node.as_str())
+    }
+}
+
+/// A set of independently budgeted nodes that tasks are placed across.
+pub struct Cluster<M = LinearCostModel> {
+    policy: PlacementPolicy,
+    nodes: Vec<(String, ResourceAwareScheduler<M>)>,
+}
+
+impl<M: CostModel> Cluster<M> {
+    /// Creates an empty cluster that places tasks by `policy`.
+    pub fn new(policy: PlacementPolicy) -> Self {
+        Cluster {
+            policy,
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Adds a node.
+    pub fn with_node(mut self, name: &str, scheduler: ResourceAwareScheduler<M>) -> Self {
+        self.nodes.push((name.to_string(), scheduler));
+        self
+    }
+
+    /// Returns the placement policy.
+    pub fn policy(&self) -> PlacementPolicy {
+        self.policy
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the cluster has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the scheduler of the node at `index`.
+    pub fn node(&self, index: usize) -> Option<&ResourceAwareScheduler<M>> {
+        self.nodes.get(index).map(|(_, scheduler)| scheduler)
+    }
+
+    /// Decides where `task` would be placed, without reserving anything.
+    pub fn assess(&self, task: &Task, risk_tolerance: f64) -> PlacementDecision {
+        let assessments: Vec<NodeAssessment> = self
+            .nodes
+            .iter()
+            .map(|(name, scheduler)| assess_node(name, scheduler, task, risk_tolerance))
+            .collect();
+        let admissible = assessments.iter().enumerate().filter(|(_, a)| a.admissible);
+        let score = |a: &NodeAssessment| match self.policy {
+            PlacementPolicy::FirstFit => 0.0,
+            PlacementPolicy::BestFit => a.headroom,
+            PlacementPolicy::WorstFit => -a.headroom,
+            PlacementPolicy::DominantResourceFairness => a.dominant_share,
+            PlacementPolicy::LowestOverloadRisk => a.risk.probability,
+        };
+        // The earliest node wins ties.
+        let node = admissible
+            .min_by(|(i, a), (j, b)| score(a).total_cmp(&score(b)).then(i.cmp(j)))
+            .map(|(i, _)| i);
+        let reason = match node {
+            Some(i) => explain(self.policy, &assessments[i]),
+            None if assessments.is_empty() => "the cluster has no nodes".to_string(),
+            None => {
+                let refusals: Vec<String> = assessments
+                    .iter()
+                    .map(|a| match a.risk.dominant() {
+                        Some(resource) => format!(
+                            "{} ({} overload risk {:.1}%)",
+                            a.node,
+                            resource,
+                            a.risk.per_resource[resource] * 100.0
+                        ),
+                        None => a.node.clone(),
+                    })
+                    .collect();
+                format!("no node can admit the task: {}", refusals.join(", "))
+            }
+        };
+        PlacementDecision {
+            policy: self.policy,
+            node,
+            assessments,
+            reason,
+        }
+    }
+
+    /// Places `task` on the node chosen by the policy, returning the
+    /// decision and, if the task was admitted, its handle on that node.
+    pub fn place(
+        &mut self,
+        task: &Task,
+        risk_tolerance: f64,
+    ) -> (PlacementDecision, Option<TaskHandle>) {
+        let decision = self.assess(task, risk_tolerance);
+        let handle = decision
+            .node
+            .and_then(|i| self.nodes[i].1.schedule(task, risk_tolerance));
+        (decision, handle)
+    }
+}
+
+fn assess_node<M: CostModel>(
+    name: &str,
+    scheduler: &ResourceAwareScheduler<M>,
+    task: &Task,
+    risk_tolerance: f64,
+) -> NodeAssessment {
+    let risk = scheduler.overload_risk(task);
+    let after = scheduler.consumed() + &scheduler.cost_model().estimate(task).mean();
+    let shares: Vec<f64> = scheduler
+        .budgets()
+        .limits()
+        .iter()
+        .filter(|&(_, limit)| limit > 0.0)
+        .map(|(resource, limit)| after.get(resource) / limit)
+        .collect();
+    let headroom = if shares.is_empty() {
+        1.0
+    } else {
+        shares.iter().map(|share| 1.0 - share).sum::<f64>() / shares.len() as f64
+    };
+    NodeAssessment {
+        node: name.to_string(),
+        admissible: risk.within(risk_tolerance),
+        risk,
+        headroom,
+        dominant_share: shares.into_iter().fold(0.0, f64::max),
+    }
+}
+
+fn explain(policy: PlacementPolicy, chosen: &NodeAssessment) -> String {
+    let detail = match policy {
+        PlacementPolicy::FirstFit => "the first node that can admit the task".to_string(),
+        PlacementPolicy::BestFit => {
+            format!("least headroom left ({:.1}%)", chosen.headroom * 100.0)
+        }
+        PlacementPolicy::WorstFit => {
+            format!("most headroom left ({:.1}%)", chosen.headroom * 100.0)
+        }
+        PlacementPolicy::DominantResourceFairness => format!(
+            "lowest dominant share ({:.1}%)",
+            chosen.dominant_share * 100.0
+        ),
+        PlacementPolicy::LowestOverloadRisk => format!(
+            "lowest overload risk ({:.2}%)",
+            chosen.risk.probability * 100.0
+        ),
+    };
+    format!("placed on {} by {}: {}", chosen.node, policy, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::{Budgets, Resource};
+    use crate::uncertainty_quantification::UncertainValue;
+
+    fn task(cpu_seconds: f64, memory: f64) -> Task {
+        Task {
+            name: "job".to_string(),
+            operations: UncertainValue::new(cpu_seconds * 1e9, cpu_seconds * 3e8),
+            data_size: memory,
+            network: false,
+            value: 1.0,
+        }
+    }
+
+    fn cluster(policy: PlacementPolicy) -> Cluster {
+        let node = |cpu: f64, memory: f64| {
+            ResourceAwareScheduler::new(
+                Budgets::new()
+                    .with(Resource::CPU, cpu)
+                    .with(Resource::MEMORY, memory),
+            )
+        };
+        // A small node, a CPU-heavy one and a memory-heavy one.
+        Cluster::new(policy)
+            .with_node("small", node(4.0, 4.0))
+            .with_node("compute", node(16.0, 16.0))
+            .with_node("storage", node(10.0, 64.0))
+    }
+
+    #[test]
+    fn test_verify_cluster_placement() {
+        // 3 ± 0.9 seconds of CPU and 4 bytes of memory. On the small node
+        // the CPU overflows with 13% probability.
+        let job = task(3.0, 4.0);
+        let place = |policy, risk_tolerance| cluster(policy).assess(&job, risk_tolerance);
+
+        let first = place(PlacementPolicy::FirstFit, 0.05);
+        assert_eq!(first.node_name(), Some("compute"));
+        assert!(!first.assessments[0].admissible);
+        assert_eq!(
+            place(PlacementPolicy::FirstFit, 0.2).node_name(),
+            Some("small")
+        );
+        let best = place(PlacementPolicy::BestFit, 0.2);
+        assert_eq!(best.node_name(), Some("small"));
+        assert!((best.assessments[0].headroom - 0.125).abs() < 1e-9);
+
+        // Storage keeps 70% of its CPU and 94% of its memory, compute 81% and
+        // 75%: storage has more headroom on average, but its scarcest
+        // resource is more heavily used.
+        assert_eq!(
+            place(PlacementPolicy::WorstFit, 0.2).node_name(),
+            Some("storage")
+        );
+        let drf = place(PlacementPolicy::DominantResourceFairness, 0.2);
+        assert_eq!(drf.node_name(), Some("compute"));
+        assert!((drf.assessments[1].dominant_share - 0.25).abs() < 1e-9);
+        assert!((drf.assessments[2].dominant_share - 0.3).abs() < 1e-9);
+        let safest = place(PlacementPolicy::LowestOverloadRisk, 0.2);
+        assert_eq!(safest.node_name(), Some("compute"));
+        assert!(
+            safest
+                .reason
+                .starts_with("placed on compute by lowest overload risk"),
+            "{}",
+            safest.reason
+        );
+
+        // Placing reserves on the chosen node only.
+        let mut cluster = cluster(PlacementPolicy::WorstFit);
+        let (decision, handle) = cluster.place(&job, 0.05);
+        assert_eq!(decision.node, Some(2));
+        assert!(handle.is_some());
+        assert_eq!(
+            cluster.node(2).unwrap().consumed().get(&Resource::MEMORY),
+            4.0
+        );
+        assert!(cluster.node(1).unwrap().consumed().is_empty());
+        drop(handle);
+
+        // A task no node can hold names what stands in the way on each.
+        let (decision, handle) = cluster.place(&task(12.0, 16.0), 0.05);
+        assert!(decision.node.is_none() && handle.is_none());
+        assert!(
+            decision
+                .reason
+                .starts_with("no node can admit the task: small (memory"),
+            "{}",
+            decision.reason
+        );
+        assert!(decision
+            .reason
+            .contains("compute (cpu overload risk 13.3%)"));
+        assert!(decision.reason.contains("storage (cpu"));
+    }
+}
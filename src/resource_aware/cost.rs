@@ -0,0 +1,548 @@
+use super::{Resource, ResourceVector, Task};
+use crate::uncertainty_quantification::UncertainValue;
+use rand::Rng;
+use statrs::distribution::{ContinuousCDF, Normal};
+use std::collections::BTreeMap;
+
+/// The probability distribution of a task's cost for one resource.
+#[derive(Debug, Clone)]
+pub enum CostDistribution {
+    /// A normally distributed cost. A zero standard deviation makes the cost exact.
+    Normal(UncertainValue),
+    /// A cost whose logarithm is normally distributed with parameters `mu`
+    /// and `sigma`: strictly positive and right-skewed, with a heavier tail
+    /// than a normal distribution of the same mean and spread.
+    LogNormal {
+        /// The mean of the cost's logarithm.
+        mu: f64,
+        /// The standard deviation of the cost's logarithm.
+        sigma: f64,
+    },
+    /// The empirical distribution of observed costs, kept sorted.
+    Empirical(Vec<f64>),
+}
+
+impl CostDistribution {
+    /// Creates an exact cost.
+    pub fn exact(value: f64) -> Self {
+        CostDistribution::Normal(UncertainValue::new(value, 0.0))
+    }
+
+    /// Creates a log-normal cost with the given mean and standard deviation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mean` is not positive.
+    pub fn log_normal(mean: f64, std_dev: f64) -> Self {
+        assert!(mean > 0.0, "a log-normal cost needs a positive mean");
+        let sigma2 = (1.0 + (std_dev / mean).powi(2)).ln();
+        CostDistribution::LogNormal {
+            mu: mean.ln() - sigma2 / 2.0,
+            sigma: sigma2.sqrt(),
+        }
+    }
+
+    /// Creates the empirical distribution of `samples`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is empty or contains NaN.
+    pub fn empirical(mut samples: Vec<f64>) -> Self {
+        assert!(!samples.is_empty(), "an empirical cost needs samples");
+        assert!(
+            samples.iter().all(|s| !s.is_nan()),
+            "samples must not be NaN"
+        );
+        samples.sort_by(f64::total_cmp);
+        CostDistribution::Empirical(samples)
+    }
+
+    /// Returns the expected cost.
+    pub fn mean(&self) -> f64 {
+        match self {
+            CostDistribution::Normal(value) => value.mean,
+            CostDistribution::LogNormal { mu, sigma } => (mu + sigma * sigma / 2.0).exp(),
+            CostDistribution::Empirical(samples) => {
+                samples.iter().sum::<f64>() / samples.len() as f64
+            }
+        }
+    }
+
+    /// Returns the standard deviation of the cost.
+    pub fn std_dev(&self) -> f64 {
+        match self {
+            CostDistribution::Normal(value) => value.std_dev,
+            CostDistribution::LogNormal { mu, sigma } => {
+                ((sigma * sigma).exp_m1() * (2.0 * mu + sigma * sigma).exp()).sqrt()
+            }
+            CostDistribution::Empirical(samples) => {
+                let mean = self.mean();
+                let var =
+                    samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+                var.sqrt()
+            }
+        }
+    }
+
+    /// Returns the probability that the cost is at most `value`.
+    pub fn cdf(&self, value: f64) -> f64 {
+        match self {
+            CostDistribution::Normal(cost) if cost.std_dev == 0.0 => {
+                if value >= cost.mean {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            CostDistribution::Normal(cost) => cost.confidence(value),
+            CostDistribution::LogNormal { mu, sigma } => {
+                if value <= 0.0 {
+                    0.0
+                } else {
+                    standard_normal().cdf((value.ln() - mu) / sigma)
+                }
+            }
+            CostDistribution::Empirical(samples) => {
+                samples.partition_point(|&s| s <= value) as f64 / samples.len() as f64
+            }
+        }
+    }
+
+    /// Returns the smallest cost `x` with `cdf(x) >= p`.
+    pub fn quantile(&self, p: f64) -> f64 {
+        let p = p.clamp(0.0, 1.0);
+        match self {
+            CostDistribution::Normal(cost) if cost.std_dev == 0.0 => cost.mean,
+            CostDistribution::Normal(cost) => {
+                cost.mean + cost.std_dev * standard_normal().inverse_cdf(p)
+            }
+            CostDistribution::LogNormal { mu, sigma } => {
+                (mu + sigma * standard_normal().inverse_cdf(p)).exp()
+            }
+            CostDistribution::Empirical(samples) => {
+                let rank = (p * samples.len() as f64).ceil() as usize;
+                samples[rank.clamp(1, samples.len()) - 1]
+            }
+        }
+    }
+}
+
+fn standard_normal() -> Normal {
+    Normal::new(0.0, 1.0).expect("valid parameters")
+}
+
+/// The estimated cost of a task across all the resources it uses.
+///
+/// Each resource has its own marginal distribution. Dependence between
+/// resources, such as CPU time and energy both growing with the same
+/// operation count, is described by pairwise correlations and reproduced
+/// when sampling through a Gaussian copula.
+#[derive(Debug, Clone, Default)]
+pub struct CostEstimate {
+    marginals: BTreeMap<Resource, CostDistribution>,
+    correlations: BTreeMap<(Resource, Resource), f64>,
+}
+
+impl CostEstimate {
+    /// Creates an estimate with no costs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the cost distribution of `resource`.
+    pub fn with(mut self, resource: Resource, cost: CostDistribution) -> Self {
+        self.marginals.insert(resource, cost);
+        self
+    }
+
+    /// Sets the correlation between the costs of two resources, clamped to
+    /// `[-1, 1]`. For non-normal marginals this is the correlation of the
+    /// underlying Gaussian copula.
+    pub fn with_correlation(mut self, a: &Resource, b: &Resource, correlation: f64) -> Self {
+        if a != b {
+            self.correlations
+                .insert(pair_key(a, b), correlation.clamp(-1.0, 1.0));
+        }
+        self
+    }
+
+    /// Returns the cost distribution of `resource`, if the task uses it.
+    pub fn get(&self, resource: &Resource) -> Option<&CostDistribution> {
+        self.marginals.get(resource)
+    }
+
+    /// Iterates over the resources and their cost distributions.
+    pub fn iter(&self) -> impl Iterator<Item = (&Resource, &CostDistribution)> {
+        self.marginals.iter()
+    }
+
+    /// Returns the expected cost of every resource.
+    pub fn mean(&self) -> ResourceVector {
+        self.iter()
+            .map(|(resource, cost)| (resource.clone(), cost.mean()))
+            .collect()
+    }
+
+    /// Returns the correlation between the costs of two resources.
+    pub fn correlation(&self, a: &Resource, b: &Resource) -> f64 {
+        if a == b {
+            return 1.0;
+        }
+        self.correlations
+            .get(&pair_key(a, b))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Draws one joint realization of the costs.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ResourceVector {
+        let resources: Vec<&Resource> = self.marginals.keys().collect();
+        let n = resources.len();
+        let mut matrix = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                matrix[i][j] = self.correlation(resources[i], resources[j]);
+            }
+        }
+        let lower = cholesky(&matrix);
+        let normal = standard_normal();
+        let independent: Vec<f64> = (0..n).map(|_| rng.sample(normal)).collect();
+        resources
+            .iter()
+            .enumerate()
+            .map(|(i, resource)| {
+                let z: f64 = (0..=i).map(|k| lower[i][k] * independent[k]).sum();
+                let cost = self.marginals[*resource].quantile(normal.cdf(z));
+                ((*resource).clone(), cost)
+            })
+            .collect()
+    }
+}
+
+fn pair_key(a: &Resource, b: &Resource) -> (Resource, Resource) {
+    if a <= b {
+        (a.clone(), b.clone())
+    } else {
+        (b.clone(), a.clone())
+    }
+}
+
+/// Cholesky factor of a correlation matrix. Pivots that are not positive,
+/// as in perfectly correlated or inconsistent inputs, are treated as zero so
+/// that the factor always exists.
+fn cholesky(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = matrix.len();
+    let mut lower = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let dot: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+            if i == j {
+                lower[i][i] = (matrix[i][i] - dot).max(0.0).sqrt();
+            } else if lower[j][j] > 0.0 {
+                lower[i][j] = (matrix[i][j] - dot) / lower[j][j];
+            }
+        }
+    }
+    lower
+}
+
+/// Turns a task into a probabilistic estimate of its resource costs.
+///
+/// Implement this to describe how tasks consume resources on a particular
+/// deployment. `observe` lets a model learn from what tasks actually used.
+pub trait CostModel {
+    /// Estimates the cost of running `task`.
+    fn estimate(&self, task: &Task) -> CostEstimate;
+
+    /// Records the resources `task` actually consumed. The default ignores
+    /// observations.
+    fn observe(&mut self, _task: &Task, _actual: &ResourceVector) {}
+}
+
+/// The property of a task that a [`LinearCostModel`] resource scales with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostDriver {
+    /// `Task::operations`, including its uncertainty.
+    Operations,
+    /// `Task::data_size`.
+    DataSize,
+    /// `Task::data_size` for tasks that use the network, zero otherwise.
+    NetworkData,
+    /// One unit per task, for costs such as license seats or file handles.
+    PerTask,
+}
+
+impl CostDriver {
+    fn value(&self, task: &Task) -> UncertainValue {
+        match self {
+            CostDriver::Operations => task.operations,
+            CostDriver::DataSize => UncertainValue::new(task.data_size, 0.0),
+            CostDriver::NetworkData if task.network => UncertainValue::new(task.data_size, 0.0),
+            CostDriver::NetworkData => UncertainValue::new(0.0, 0.0),
+            CostDriver::PerTask => UncertainValue::new(1.0, 0.0),
+        }
+    }
+}
+
+/// The fitted cost of one resource in a [`LinearCostModel`]:
+/// `intercept + slope * driver`, plus normal noise with `residual_std`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearCost {
+    /// The task property the cost scales with.
+    pub driver: CostDriver,
+    /// The cost per unit of the driver.
+    pub slope: f64,
+    /// The fixed cost of every task.
+    pub intercept: f64,
+    /// The standard deviation of the cost around the fitted line.
+    pub residual_std: f64,
+    /// The number of observations the fit is based on; zero for the prior.
+    pub observations: usize,
+}
+
+/// Running sums for an online least-squares fit of `y = a + b x`.
+#[derive(Debug, Clone, Copy, Default)]
+struct Regression {
+    n: f64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_xy: f64,
+    sum_yy: f64,
+}
+
+impl Regression {
+    fn add(&mut self, x: f64, y: f64) {
+        self.n += 1.0;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_xx += x * x;
+        self.sum_xy += x * y;
+        self.sum_yy += y * y;
+    }
+
+    /// Returns `(intercept, slope, residual_std)`, or `None` while there
+    /// are too few observations.
+    ///
+    /// A driver that never varies, such as [`CostDriver::PerTask`], cannot
+    /// separate slope from intercept; the cost is then taken to be
+    /// proportional to the driver.
+    fn fit(&self) -> Option<(f64, f64, f64)> {
+        if self.n < 2.0 {
+            return None;
+        }
+        let sxx = self.sum_xx - self.sum_x * self.sum_x / self.n;
+        let sxy = self.sum_xy - self.sum_x * self.sum_y / self.n;
+        let syy = self.sum_yy - self.sum_y * self.sum_y / self.n;
+        if sxx > f64::EPSILON * self.sum_xx {
+            if self.n < 3.0 {
+                return None;
+            }
+            let slope = sxy / sxx;
+            let intercept = (self.sum_y - slope * self.sum_x) / self.n;
+            let residual = (syy - slope * sxy).max(0.0) / (self.n - 2.0);
+            Some((intercept, slope, residual.sqrt()))
+        } else if self.sum_x != 0.0 {
+            let residual = syy.max(0.0) / (self.n - 1.0);
+            Some((0.0, self.sum_y / self.sum_x, residual.sqrt()))
+        } else {
+            None
+        }
+    }
+}
+
+/// A cost model in which each resource grows linearly with one property of
+/// the task, and which calibrates itself from observed executions.
+///
+/// `new` starts from the scheduler's historical assumptions: one second of
+/// CPU and one joule per 10⁹ operations, memory equal to the data size, and
+/// bandwidth equal to the data size for networked tasks. Each call to
+/// `observe` refits the affected resources by least squares once enough
+/// observations are available. Resources that share a driver are reported as
+/// correlated.
+#[derive(Debug, Clone)]
+pub struct LinearCostModel {
+    costs: BTreeMap<Resource, (LinearCost, Regression)>,
+}
+
+impl LinearCostModel {
+    /// Creates a model with the default CPU, energy, memory and bandwidth costs.
+    pub fn new() -> Self {
+        LinearCostModel {
+            costs: BTreeMap::new(),
+        }
+        .with_resource(Resource::CPU, CostDriver::Operations, 1e-9)
+        .with_resource(Resource::ENERGY, CostDriver::Operations, 1e-9)
+        .with_resource(Resource::MEMORY, CostDriver::DataSize, 1.0)
+        .with_resource(Resource::BANDWIDTH, CostDriver::NetworkData, 1.0)
+    }
+
+    /// Adds or replaces a resource whose prior cost is `slope` per unit of `driver`.
+    pub fn with_resource(mut self, resource: Resource, driver: CostDriver, slope: f64) -> Self {
+        let cost = LinearCost {
+            driver,
+            slope,
+            intercept: 0.0,
+            residual_std: 0.0,
+            observations: 0,
+        };
+        self.costs.insert(resource, (cost, Regression::default()));
+        self
+    }
+
+    /// Returns the current fit for `resource`.
+    pub fn cost(&self, resource: &Resource) -> Option<&LinearCost> {
+        self.costs.get(resource).map(|(cost, _)| cost)
+    }
+}
+
+impl Default for LinearCostModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CostModel for LinearCostModel {
+    fn estimate(&self, task: &Task) -> CostEstimate {
+        let mut estimate = CostEstimate::new();
+        let mut shared = Vec::new();
+        for (resource, (cost, _)) in &self.costs {
+            let driver = cost.driver.value(task);
+            let driven_std = cost.slope * driver.std_dev;
+            let std_dev = driven_std.hypot(cost.residual_std);
+            let mean = cost.intercept + cost.slope * driver.mean;
+            estimate = estimate.with(
+                resource.clone(),
+                CostDistribution::Normal(UncertainValue::new(mean, std_dev)),
+            );
+            if driven_std != 0.0 {
+                shared.push((resource, cost.driver, driven_std, std_dev));
+            }
+        }
+        // Costs that scale the same uncertain driver move together; only
+        // their residual noise is independent.
+        for (i, &(a, driver_a, driven_a, std_a)) in shared.iter().enumerate() {
+            for &(b, driver_b, driven_b, std_b) in &shared[i + 1..] {
+                if driver_a == driver_b {
+                    estimate =
+                        estimate.with_correlation(a, b, driven_a * driven_b / (std_a * std_b));
+                }
+            }
+        }
+        estimate
+    }
+
+    fn observe(&mut self, task: &Task, actual: &ResourceVector) {
+        for (resource, used) in actual.iter() {
+            let Some((cost, regression)) = self.costs.get_mut(resource) else {
+                continue;
+            };
+            regression.add(cost.driver.value(task).mean, used);
+            if let Some((intercept, slope, residual_std)) = regression.fit() {
+                cost.intercept = intercept;
+                cost.slope = slope;
+                cost.residual_std = residual_std;
+                cost.observations = regression.n as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::{Budgets, ResourceAwareScheduler};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    fn task(operations: f64, data_size: f64) -> Task {
+        Task {
+            name: "job".to_string(),
+            operations: UncertainValue::new(operations, operations / 10.0),
+            data_size,
+            network: true,
+            value: 1.0,
+        }
+    }
+
+    /// A model where every resource has a heavy-tailed cost.
+    struct HeavyTailed;
+
+    impl CostModel for HeavyTailed {
+        fn estimate(&self, task: &Task) -> CostEstimate {
+            let cpu = task.operations.mean / 1e9;
+            CostEstimate::new().with(Resource::CPU, CostDistribution::log_normal(cpu, 2.0 * cpu))
+        }
+    }
+
+    #[test]
+    fn test_verify_cost_models() {
+        let model = LinearCostModel::new();
+        let estimate = model.estimate(&task(2e9, 1e6));
+        let cpu = estimate.get(&Resource::CPU).unwrap();
+        assert!((cpu.mean() - 2.0).abs() < 1e-12);
+        assert!((cpu.std_dev() - 0.2).abs() < 1e-12);
+        assert_eq!(estimate.get(&Resource::BANDWIDTH).unwrap().mean(), 1e6);
+        assert!((estimate.correlation(&Resource::CPU, &Resource::ENERGY) - 1.0).abs() < 1e-12);
+        assert_eq!(estimate.correlation(&Resource::CPU, &Resource::MEMORY), 0.0);
+
+        // Calibrate against a machine that needs 0.5s of setup plus 3s per
+        // 10⁹ operations, with some noise.
+        let mut model = LinearCostModel::new();
+        let mut rng = SmallRng::seed_from_u64(1);
+        for i in 1..=200 {
+            let t = task(i as f64 * 1e7, 1e6);
+            let cpu = 0.5 + 3e-9 * t.operations.mean + rng.gen_range(-0.1..0.1);
+            model.observe(&t, &ResourceVector::new().with(Resource::CPU, cpu));
+        }
+        let fit = model.cost(&Resource::CPU).unwrap();
+        assert_eq!(fit.observations, 200);
+        assert!((fit.slope - 3e-9).abs() < 1e-10, "slope {}", fit.slope);
+        assert!((fit.intercept - 0.5).abs() < 0.05);
+        assert!((fit.residual_std - 0.2 / 12f64.sqrt()).abs() < 0.01);
+        assert_eq!(model.cost(&Resource::ENERGY).unwrap().observations, 0);
+
+        // Correlated sampling reproduces the requested dependence.
+        let estimate = CostEstimate::new()
+            .with(
+                Resource::CPU,
+                CostDistribution::Normal(UncertainValue::new(10.0, 1.0)),
+            )
+            .with(Resource::ENERGY, CostDistribution::log_normal(5.0, 1.0))
+            .with(
+                Resource::MEMORY,
+                CostDistribution::empirical(vec![3.0, 1.0, 2.0]),
+            )
+            .with_correlation(&Resource::ENERGY, &Resource::CPU, 0.8);
+        let draws: Vec<_> = (0..5_000).map(|_| estimate.sample(&mut rng)).collect();
+        let column = |r: Resource| draws.iter().map(|d| d.get(&r)).collect::<Vec<_>>();
+        let (cpu, energy) = (column(Resource::CPU), column(Resource::ENERGY));
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+        let (mc, me) = (mean(&cpu), mean(&energy));
+        let cov = mean(
+            &cpu.iter()
+                .zip(&energy)
+                .map(|(c, e)| (c - mc) * (e - me))
+                .collect::<Vec<_>>(),
+        );
+        let sd =
+            |v: &[f64], m: f64| mean(&v.iter().map(|x| (x - m).powi(2)).collect::<Vec<_>>()).sqrt();
+        let rho = cov / (sd(&cpu, mc) * sd(&energy, me));
+        // The skewed energy marginal pulls the linear correlation slightly
+        // below the copula's 0.8.
+        assert!((rho - 0.78).abs() < 0.05, "correlation {rho}");
+        assert!((me - 5.0).abs() < 0.1);
+        assert!(column(Resource::MEMORY)
+            .iter()
+            .all(|m| [1.0, 2.0, 3.0].contains(m)));
+        assert_eq!(estimate.get(&Resource::MEMORY).unwrap().cdf(2.5), 2.0 / 3.0);
+
+        // A heavy tail makes the same mean cost too risky.
+        let budgets = Budgets::new().with(Resource::CPU, 3.0);
+        let job = task(1e9, 1e6);
+        let gaussian = ResourceAwareScheduler::new(budgets.clone());
+        let heavy = ResourceAwareScheduler::with_cost_model(budgets, HeavyTailed);
+        assert!(gaussian.can_schedule(&job, 0.05));
+        assert!(!heavy.can_schedule(&job, 0.05));
+    }
+}
@@ -0,0 +1,161 @@
+use super::cost::CostEstimate;
+use super::risk::{OverloadRisk, RiskMode};
+use super::{Budgets, Resource, ResourceVector};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How one budget stood when a task was considered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceCheck {
+    /// The budget's limit.
+    pub limit: f64,
+    /// The amount already committed to other tasks.
+    pub committed: f64,
+    /// The task's expected cost.
+    pub expected_cost: f64,
+    /// What would be left of the budget after the task's expected cost:
+    /// `limit - committed - expected_cost`. Negative if the task is expected
+    /// not to fit.
+    pub headroom: f64,
+    /// The probability that the task's cost exceeds what is left.
+    pub overload_probability: f64,
+}
+
+/// Why a task was admitted or rejected.
+///
+/// Decisions serialize with serde, e.g. to one JSON line per decision in an
+/// audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleDecision {
+    /// The name of the task.
+    pub task: String,
+    /// Whether the task was admitted.
+    pub admitted: bool,
+    /// The risk tolerance the task was checked against.
+    pub risk_tolerance: f64,
+    /// How the per-budget probabilities were combined.
+    pub risk_mode: RiskMode,
+    /// The combined overload probability compared against the tolerance.
+    pub overload_probability: f64,
+    /// Each budget the task draws on.
+    pub resources: BTreeMap<Resource, ResourceCheck>,
+    /// The budget closest to being exceeded: the most likely to overflow,
+    /// or, if none can, the one with the least headroom relative to its
+    /// limit. `None` if the task uses no budgeted resource.
+    pub binding: Option<Resource>,
+}
+
+impl ScheduleDecision {
+    pub(crate) fn new(
+        task: &str,
+        budgets: &Budgets,
+        cost: &CostEstimate,
+        remaining: &ResourceVector,
+        risk: OverloadRisk,
+        risk_tolerance: f64,
+    ) -> Self {
+        let expected = cost.mean();
+        let resources: BTreeMap<Resource, ResourceCheck> = risk
+            .per_resource
+            .iter()
+            .map(|(resource, &overload_probability)| {
+                let limit = budgets.limit(resource).unwrap_or(f64::INFINITY);
+                let left = remaining.get(resource);
+                let expected_cost = expected.get(resource);
+                let check = ResourceCheck {
+                    limit,
+                    committed: limit - left,
+                    expected_cost,
+                    headroom: left - expected_cost,
+                    overload_probability,
+                };
+                (resource.clone(), check)
+            })
+            .collect();
+        let tightness = |check: &ResourceCheck| {
+            let relative_headroom = check.headroom / check.limit.abs().max(f64::MIN_POSITIVE);
+            (check.overload_probability, -relative_headroom)
+        };
+        let binding = resources
+            .iter()
+            .max_by(|(_, a), (_, b)| {
+                let ((pa, ha), (pb, hb)) = (tightness(a), tightness(b));
+                pa.total_cmp(&pb).then(ha.total_cmp(&hb))
+            })
+            .map(|(resource, _)| resource.clone());
+        ScheduleDecision {
+            task: task.to_string(),
+            admitted: risk.within(risk_tolerance),
+            risk_tolerance,
+            risk_mode: risk.mode,
+            overload_probability: risk.probability,
+            resources,
+            binding,
+        }
+    }
+
+    /// Returns the check of the binding budget.
+    pub fn binding_check(&self) -> Option<&ResourceCheck> {
+        self.binding
+            .as_ref()
+            .map(|resource| &self.resources[resource])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::{ResourceAwareScheduler, Task};
+    use crate::uncertainty_quantification::UncertainValue;
+
+    fn task(operations: UncertainValue, data_size: f64) -> Task {
+        Task {
+            name: "etl".to_string(),
+            operations,
+            data_size,
+            network: false,
+            value: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_verify_schedule_decision() {
+        let budgets = Budgets::new()
+            .with(Resource::CPU, 10.0)
+            .with(Resource::ENERGY, 100.0)
+            .with(Resource::MEMORY, 8e9);
+        let mut scheduler = ResourceAwareScheduler::new(budgets);
+
+        // With an exact cost nothing can overflow, so the budget with the
+        // least headroom relative to its limit binds: memory, half used.
+        let exact = task(UncertainValue::new(1e9, 0.0), 4e9);
+        let decision = scheduler.explain(&exact, 0.05);
+        assert!(decision.admitted);
+        assert_eq!(decision.overload_probability, 0.0);
+        assert_eq!(decision.binding, Some(Resource::MEMORY));
+        assert!((decision.resources[&Resource::CPU].headroom - 9.0).abs() < 1e-9);
+
+        // 3 ± 1 seconds of CPU and joules: after two such tasks the CPU is
+        // expected to have a second to spare, but a 16% chance to overflow.
+        let job = task(UncertainValue::new(3e9, 1e9), 1e9);
+        let (decision, first) = scheduler.schedule_explained(&job, 0.05);
+        assert!(decision.admitted && first.is_some());
+        assert_eq!(decision.binding, Some(Resource::CPU));
+        let (_, second) = scheduler.schedule_explained(&job, 0.05);
+        assert!(second.is_some());
+        let (decision, third) = scheduler.schedule_explained(&job, 0.05);
+        assert!(!decision.admitted && third.is_none());
+        assert_eq!(decision.binding, Some(Resource::CPU));
+        let cpu = decision.binding_check().unwrap();
+        assert!((cpu.committed - 6.0).abs() < 1e-9);
+        assert!((cpu.headroom - 1.0).abs() < 1e-9);
+        assert!((cpu.overload_probability - 0.1587).abs() < 1e-3);
+        assert_eq!(decision.resources[&Resource::MEMORY].headroom, 5e9);
+
+        let record = serde_json::to_string(&decision).unwrap();
+        assert!(record.contains(r#""binding":"cpu""#), "{record}");
+        let restored: ScheduleDecision = serde_json::from_str(&record).unwrap();
+        assert_eq!(restored, decision);
+        drop((first, second));
+    }
+}
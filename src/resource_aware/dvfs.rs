@@ -0,0 +1,286 @@
+use super::cost::{CostDistribution, CostEstimate, CostModel, LinearCostModel};
+use super::{Resource, ResourceVector, Task};
+use crate::uncertainty_quantification::UncertainValue;
+use statrs::distribution::{ContinuousCDF, Normal};
+use std::time::Duration;
+
+/// A frequency/voltage pair a processor can run at (a P-state).
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatingPoint {
+    /// The name of the operating point, e.g. "Eco".
+    pub name: String,
+    /// The clock frequency, in hertz.
+    pub frequency: f64,
+    /// The supply voltage, in volts.
+    pub voltage: f64,
+}
+
+impl OperatingPoint {
+    /// Creates an operating point.
+    pub fn new(name: &str, frequency: f64, voltage: f64) -> Self {
+        OperatingPoint {
+            name: name.to_string(),
+            frequency,
+            voltage,
+        }
+    }
+
+    /// Returns the dynamic power drawn at this point, `C·V²·f`, in watts,
+    /// for a switched capacitance of `capacitance` farads.
+    pub fn power(&self, capacitance: f64) -> f64 {
+        capacitance * self.voltage.powi(2) * self.frequency
+    }
+}
+
+/// The operating point chosen for a task and what running there costs.
+#[derive(Debug, Clone)]
+pub struct DvfsPlan {
+    /// The selected operating point.
+    pub point: OperatingPoint,
+    /// The execution time at that point, in seconds.
+    pub duration: UncertainValue,
+    /// The energy used at that point, in joules.
+    pub energy: UncertainValue,
+    /// The probability that the task finishes by the deadline.
+    pub deadline_probability: f64,
+}
+
+/// Picks processor operating points for tasks by dynamic voltage and
+/// frequency scaling.
+///
+/// The processor is modeled as executing one operation per cycle, so a task
+/// of `n` operations takes `n / f` seconds and uses `C·V²·n` joules at an
+/// operating point. Energy does not depend on frequency, only on voltage, but
+/// lower voltages only sustain lower frequencies: the cheapest point is the
+/// slowest one that still finishes in time.
+#[derive(Debug, Clone)]
+pub struct DvfsController {
+    capacitance: f64,
+    points: Vec<OperatingPoint>,
+}
+
+impl DvfsController {
+    /// Creates a controller with no operating points for a processor with a
+    /// switched capacitance of `capacitance` farads.
+    pub fn new(capacitance: f64) -> Self {
+        DvfsController {
+            capacitance,
+            points: Vec::new(),
+        }
+    }
+
+    /// Adds an operating point.
+    pub fn with_point(mut self, point: OperatingPoint) -> Self {
+        self.points.push(point);
+        self
+    }
+
+    /// Returns the operating points.
+    pub fn points(&self) -> &[OperatingPoint] {
+        &self.points
+    }
+
+    /// Returns the execution time and energy of `operations` at `point`.
+    pub fn cost(
+        &self,
+        point: &OperatingPoint,
+        operations: &UncertainValue,
+    ) -> (UncertainValue, UncertainValue) {
+        let duration = UncertainValue::new(
+            operations.mean / point.frequency,
+            operations.std_dev / point.frequency,
+        );
+        let joules_per_operation = point.power(self.capacitance) / point.frequency;
+        let energy = UncertainValue::new(
+            operations.mean * joules_per_operation,
+            operations.std_dev * joules_per_operation,
+        );
+        (duration, energy)
+    }
+
+    /// Selects the lowest-energy operating point at which `operations`
+    /// finish within `deadline` with probability at least `confidence`.
+    /// Returns `None` if even the fastest point is too slow.
+    pub fn select(
+        &self,
+        operations: &UncertainValue,
+        deadline: Duration,
+        confidence: f64,
+    ) -> Option<DvfsPlan> {
+        self.points
+            .iter()
+            .map(|point| self.plan(point, operations, deadline))
+            .filter(|plan| plan.deadline_probability >= confidence)
+            .min_by(|a, b| {
+                a.energy
+                    .mean
+                    .total_cmp(&b.energy.mean)
+                    .then(b.point.frequency.total_cmp(&a.point.frequency))
+            })
+    }
+
+    /// Returns the plan for the fastest operating point, or `None` if there
+    /// are no operating points.
+    pub fn fastest(&self, operations: &UncertainValue, deadline: Duration) -> Option<DvfsPlan> {
+        self.points
+            .iter()
+            .max_by(|a, b| a.frequency.total_cmp(&b.frequency))
+            .map(|point| self.plan(point, operations, deadline))
+    }
+
+    fn plan(
+        &self,
+        point: &OperatingPoint,
+        operations: &UncertainValue,
+        deadline: Duration,
+    ) -> DvfsPlan {
+        let (duration, energy) = self.cost(point, operations);
+        let deadline = deadline.as_secs_f64();
+        let deadline_probability = if duration.std_dev == 0.0 {
+            if duration.mean <= deadline {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            let normal = Normal::new(duration.mean, duration.std_dev).expect("valid parameters");
+            normal.cdf(deadline)
+        };
+        DvfsPlan {
+            point: point.clone(),
+            duration,
+            energy,
+            deadline_probability,
+        }
+    }
+}
+
+impl Default for DvfsController {
+    /// A 1 nF processor with five operating points from 0.8 GHz at 0.7 V to
+    /// 3 GHz at 1.5 V. At the balanced point, 10⁹ operations take 1.21 J,
+    /// close to the joule per 10⁹ operations of the default cost model.
+    fn default() -> Self {
+        DvfsController::new(1e-9)
+            .with_point(OperatingPoint::new("Eco", 0.8e9, 0.7))
+            .with_point(OperatingPoint::new("Low", 1.2e9, 0.9))
+            .with_point(OperatingPoint::new("Balanced", 1.8e9, 1.1))
+            .with_point(OperatingPoint::new("Performance", 2.4e9, 1.3))
+            .with_point(OperatingPoint::new("Turbo", 3.0e9, 1.5))
+    }
+}
+
+/// A cost model that runs every task at the operating point a
+/// [`DvfsController`] selects for a deadline.
+///
+/// CPU time and energy come from the selected point; every other resource
+/// comes from the base model. A task that cannot meet the deadline at any
+/// point is costed at the fastest one, where it will run flat out.
+pub struct DvfsCostModel<M = LinearCostModel> {
+    controller: DvfsController,
+    deadline: Duration,
+    confidence: f64,
+    base: M,
+}
+
+impl DvfsCostModel {
+    /// Creates a model that plans for tasks to meet `deadline` with
+    /// probability `confidence`, on top of the default cost model.
+    pub fn new(controller: DvfsController, deadline: Duration, confidence: f64) -> Self {
+        Self::with_base_model(controller, deadline, confidence, LinearCostModel::new())
+    }
+}
+
+impl<M: CostModel> DvfsCostModel<M> {
+    /// Creates a model that takes resources other than CPU and energy from `base`.
+    pub fn with_base_model(
+        controller: DvfsController,
+        deadline: Duration,
+        confidence: f64,
+        base: M,
+    ) -> Self {
+        DvfsCostModel {
+            controller,
+            deadline,
+            confidence,
+            base,
+        }
+    }
+
+    /// Returns the operating point plan for `task`, or `None` if the
+    /// controller has no operating points.
+    pub fn plan(&self, task: &Task) -> Option<DvfsPlan> {
+        self.controller
+            .select(&task.operations, self.deadline, self.confidence)
+            .or_else(|| self.controller.fastest(&task.operations, self.deadline))
+    }
+}
+
+impl<M: CostModel> CostModel for DvfsCostModel<M> {
+    fn estimate(&self, task: &Task) -> CostEstimate {
+        let estimate = self.base.estimate(task);
+        let Some(plan) = self.plan(task) else {
+            return estimate;
+        };
+        let estimate = estimate
+            .with(Resource::CPU, CostDistribution::Normal(plan.duration))
+            .with(Resource::ENERGY, CostDistribution::Normal(plan.energy));
+        if plan.duration.std_dev > 0.0 {
+            // Both scale the same operation count.
+            estimate.with_correlation(&Resource::CPU, &Resource::ENERGY, 1.0)
+        } else {
+            estimate
+        }
+    }
+
+    fn observe(&mut self, task: &Task, actual: &ResourceVector) {
+        self.base.observe(task, actual);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::{Budgets, ResourceAwareScheduler};
+
+    #[test]
+    fn test_verify_dvfs_selection() {
+        let controller = DvfsController::default();
+        let operations = UncertainValue::new(1e9, 1e8);
+        let second = Duration::from_secs(1);
+
+        // With half a chance of overrunning, 1.2 GHz is fast enough and uses
+        // 0.81 J rather than the 1.21 J of the balanced point.
+        let plan = controller.select(&operations, second, 0.5).unwrap();
+        assert_eq!(plan.point.name, "Low");
+        assert!((plan.energy.mean - 0.81).abs() < 1e-9);
+        assert!((plan.duration.mean - 1.0 / 1.2).abs() < 1e-9);
+
+        // At 99% confidence the Low point's 1.03s tail misses the deadline.
+        let plan = controller.select(&operations, second, 0.99).unwrap();
+        assert_eq!(plan.point.name, "Balanced");
+        assert!(plan.deadline_probability >= 0.99);
+        assert!(controller
+            .select(&operations, Duration::from_millis(100), 0.5)
+            .is_none());
+
+        // An energy budget of 1 J: the default model assumes 1 J per 10⁹
+        // operations and refuses, while planning for the deadline admits.
+        let task = Task {
+            name: "inference".to_string(),
+            operations,
+            data_size: 0.0,
+            network: false,
+            value: 1.0,
+        };
+        let budgets = Budgets::new().with(Resource::ENERGY, 1.0);
+        let scheduler = ResourceAwareScheduler::new(budgets.clone());
+        assert!(!scheduler.can_schedule(&task, 0.05));
+        let model = DvfsCostModel::new(controller, second, 0.95);
+        assert_eq!(model.plan(&task).unwrap().point.name, "Low");
+        let mut scheduler = ResourceAwareScheduler::with_cost_model(budgets, model);
+        let handle = scheduler.schedule(&task, 0.05).unwrap();
+        assert!((handle.estimated().get(&Resource::ENERGY) - 0.81).abs() < 1e-9);
+        assert!((handle.estimated().get(&Resource::CPU) - 1.0 / 1.2).abs() < 1e-9);
+        handle.cancel();
+    }
+}
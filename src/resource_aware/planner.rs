@@ -0,0 +1,422 @@
+use super::cost::CostModel;
+use super::risk::RiskMode;
+use super::{Resource, ResourceAwareScheduler, ResourceVector, Task};
+use statrs::distribution::{ContinuousCDF, Normal};
+
+/// How a [`BatchPlan`] is searched for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanningStrategy {
+    /// Branch and bound over every subset. Optimal, but exponential in the
+    /// number of candidates.
+    Exact,
+    /// Adds tasks in order of value per unit of normalized resource use,
+    /// keeping every one that still fits.
+    Greedy,
+    /// Solves the linear relaxation of the knapsack, then rounds it by adding
+    /// tasks in order of their fractional weight. Also reports an upper bound
+    /// on the value any plan can reach.
+    LpRelaxation,
+}
+
+/// The subset of candidate tasks chosen by a batch planner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchPlan {
+    /// The strategy that produced the plan.
+    pub strategy: PlanningStrategy,
+    /// Indices of the selected tasks in the candidate slice, ascending.
+    pub selected: Vec<usize>,
+    /// The total `Task::value` of the selected tasks.
+    pub value: f64,
+    /// The summed expected cost of the selected tasks.
+    pub expected_usage: ResourceVector,
+    /// For `LpRelaxation`, the optimum of the relaxation with costs at their
+    /// means, which no plan can exceed when the risk tolerance is at most 50%.
+    pub upper_bound: Option<f64>,
+}
+
+/// The largest number of candidates [`PlanningStrategy::Exact`] accepts.
+pub const MAX_EXACT_CANDIDATES: usize = 24;
+
+/// One candidate's cost on each constrained resource.
+struct Candidate {
+    value: f64,
+    mean: Vec<f64>,
+    variance: Vec<f64>,
+}
+
+/// A multi-dimensional knapsack with chance constraints.
+///
+/// The summed cost of a set of tasks on each resource is approximated as
+/// normal with the summed means and variances of the individual costs. This
+/// treats tasks as independent, ignores the shape of each cost's
+/// distribution and ignores correlations between resources. A set is
+/// feasible if, on every constrained resource, the probability of exceeding
+/// what remains of the budget is below the risk tolerance. Under either
+/// joint [`RiskMode`], `MonteCarlo` included, the sum of those probabilities
+/// must be below the tolerance instead, the union bound on overloading any
+/// budget.
+///
+/// This approximates the test `schedule` applies to single tasks rather than
+/// repeating it, so for log-normal or empirical costs, correlated costs, or
+/// `MonteCarlo` risk, the two can disagree about a task near the limit.
+struct Problem {
+    remaining: Vec<f64>,
+    risk_tolerance: f64,
+    union_bound: bool,
+    candidates: Vec<Candidate>,
+}
+
+impl Problem {
+    fn new<M: CostModel>(
+        scheduler: &ResourceAwareScheduler<M>,
+        tasks: &[Task],
+        risk_tolerance: f64,
+    ) -> (Self, Vec<Resource>) {
+        let (resources, remaining): (Vec<Resource>, Vec<f64>) = scheduler
+            .remaining_of(
+                scheduler
+                    .budgets()
+                    .limits()
+                    .iter()
+                    .map(|(resource, _)| resource),
+            )
+            .iter()
+            .map(|(resource, left)| (resource.clone(), left))
+            .unzip();
+        let candidates = tasks
+            .iter()
+            .map(|task| {
+                let estimate = scheduler.cost_model().estimate(task);
+                let cost = |resource: &Resource| estimate.get(resource);
+                Candidate {
+                    value: task.value,
+                    mean: resources
+                        .iter()
+                        .map(|r| cost(r).map_or(0.0, |c| c.mean()))
+                        .collect(),
+                    variance: resources
+                        .iter()
+                        .map(|r| cost(r).map_or(0.0, |c| c.std_dev().powi(2)))
+                        .collect(),
+                }
+            })
+            .collect();
+        let problem = Problem {
+            remaining,
+            risk_tolerance,
+            union_bound: scheduler.risk_mode() != RiskMode::PerResource,
+            candidates,
+        };
+        (problem, resources)
+    }
+
+    fn dimensions(&self) -> usize {
+        self.remaining.len()
+    }
+
+    fn feasible(&self, mean: &[f64], variance: &[f64]) -> bool {
+        let normal = Normal::new(0.0, 1.0).expect("valid parameters");
+        let mut overload_probs = (0..self.dimensions()).map(|r| {
+            let slack = self.remaining[r] - mean[r];
+            if variance[r] == 0.0 {
+                if slack >= 0.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            } else {
+                1.0 - normal.cdf(slack / variance[r].sqrt())
+            }
+        });
+        if self.dimensions() == 0 {
+            true
+        } else if self.union_bound {
+            overload_probs.sum::<f64>() < self.risk_tolerance
+        } else {
+            overload_probs.all(|p| p < self.risk_tolerance)
+        }
+    }
+
+    /// Tries to add candidate `i` to the running totals; returns whether it fit.
+    fn try_add(&self, i: usize, mean: &mut [f64], variance: &mut [f64]) -> bool {
+        let candidate = &self.candidates[i];
+        for r in 0..self.dimensions() {
+            mean[r] += candidate.mean[r];
+            variance[r] += candidate.variance[r];
+        }
+        if self.feasible(mean, variance) {
+            return true;
+        }
+        for r in 0..self.dimensions() {
+            mean[r] -= candidate.mean[r];
+            variance[r] -= candidate.variance[r];
+        }
+        false
+    }
+
+    /// Tasks worth scheduling, most value per unit of normalized expected
+    /// resource use first.
+    fn by_density(&self) -> Vec<usize> {
+        let size = |c: &Candidate| -> f64 {
+            (0..self.dimensions())
+                .map(|r| c.mean[r].max(0.0) / self.remaining[r].max(f64::MIN_POSITIVE))
+                .sum()
+        };
+        let mut order: Vec<usize> = (0..self.candidates.len())
+            .filter(|&i| self.candidates[i].value > 0.0)
+            .collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.candidates[a], &self.candidates[b]);
+            (b.value * size(a)).total_cmp(&(a.value * size(b)))
+        });
+        order
+    }
+
+    fn greedy(&self, order: &[usize]) -> Vec<usize> {
+        let mut mean = vec![0.0; self.dimensions()];
+        let mut variance = vec![0.0; self.dimensions()];
+        order
+            .iter()
+            .copied()
+            .filter(|&i| self.try_add(i, &mut mean, &mut variance))
+            .collect()
+    }
+
+    fn exact(&self) -> Vec<usize> {
+        let order = self.by_density();
+        let mut suffix_value = vec![0.0; order.len() + 1];
+        for k in (0..order.len()).rev() {
+            suffix_value[k] = suffix_value[k + 1] + self.candidates[order[k]].value;
+        }
+        let mut search = ExactSearch {
+            problem: self,
+            order: &order,
+            suffix_value: &suffix_value,
+            chosen: Vec::new(),
+            mean: vec![0.0; self.dimensions()],
+            variance: vec![0.0; self.dimensions()],
+            best: self.greedy(&order),
+            best_value: 0.0,
+        };
+        search.best_value = search.best.iter().map(|&i| self.candidates[i].value).sum();
+        search.branch(0, 0.0);
+        search.best
+    }
+
+    fn lp_relaxation(&self) -> (Vec<usize>, f64) {
+        let order = self.by_density();
+        let values: Vec<f64> = order.iter().map(|&i| self.candidates[i].value).collect();
+        let weights: Vec<Vec<f64>> = (0..self.dimensions())
+            .map(|r| {
+                order
+                    .iter()
+                    .map(|&i| self.candidates[i].mean[r].max(0.0))
+                    .collect()
+            })
+            .collect();
+        let capacity: Vec<f64> = self.remaining.iter().map(|b| b.max(0.0)).collect();
+        let (bound, fractions) = solve_packing_lp(&values, &weights, &capacity);
+
+        let mut rounding: Vec<usize> = (0..order.len()).collect();
+        rounding.sort_by(|&a, &b| fractions[b].total_cmp(&fractions[a]));
+        let ranked: Vec<usize> = rounding.into_iter().map(|k| order[k]).collect();
+        (self.greedy(&ranked), bound)
+    }
+}
+
+struct ExactSearch<'a> {
+    problem: &'a Problem,
+    order: &'a [usize],
+    suffix_value: &'a [f64],
+    chosen: Vec<usize>,
+    mean: Vec<f64>,
+    variance: Vec<f64>,
+    best: Vec<usize>,
+    best_value: f64,
+}
+
+impl ExactSearch<'_> {
+    fn branch(&mut self, k: usize, value: f64) {
+        if value > self.best_value {
+            self.best_value = value;
+            self.best = self.chosen.clone();
+        }
+        if k == self.order.len() || value + self.suffix_value[k] <= self.best_value {
+            return;
+        }
+        let i = self.order[k];
+        if self.problem.try_add(i, &mut self.mean, &mut self.variance) {
+            self.chosen.push(i);
+            self.branch(k + 1, value + self.problem.candidates[i].value);
+            self.chosen.pop();
+            let candidate = &self.problem.candidates[i];
+            for r in 0..self.problem.dimensions() {
+                self.mean[r] -= candidate.mean[r];
+                self.variance[r] -= candidate.variance[r];
+            }
+        }
+        self.branch(k + 1, value);
+    }
+}
+
+/// Maximizes `values · x` subject to `weights · x <= capacity` and
+/// `0 <= x <= 1` with the simplex method, using Bland's rule so that it
+/// always terminates. `capacity` must be non-negative, which makes `x = 0`
+/// a feasible starting point. Returns the optimum and the optimal `x`.
+fn solve_packing_lp(values: &[f64], weights: &[Vec<f64>], capacity: &[f64]) -> (f64, Vec<f64>) {
+    const EPS: f64 = 1e-12;
+    let n = values.len();
+    let rows = weights.len() + n;
+    let cols = n + rows;
+    // Constraint rows: the resource constraints, then x_j <= 1. Each has its
+    // own slack variable, which forms the initial basis.
+    let mut tableau = vec![vec![0.0; cols + 1]; rows];
+    for (r, row) in weights.iter().enumerate() {
+        tableau[r][..n].copy_from_slice(row);
+        tableau[r][n + r] = 1.0;
+        tableau[r][cols] = capacity[r];
+    }
+    for j in 0..n {
+        let r = weights.len() + j;
+        tableau[r][j] = 1.0;
+        tableau[r][n + r] = 1.0;
+        tableau[r][cols] = 1.0;
+    }
+    let mut objective: Vec<f64> = values.iter().map(|v| -v).collect();
+    objective.resize(cols + 1, 0.0);
+    let mut basis: Vec<usize> = (n..cols).collect();
+
+    while let Some(entering) = (0..cols).find(|&c| objective[c] < -EPS) {
+        let leaving = (0..rows)
+            .filter(|&r| tableau[r][entering] > EPS)
+            .min_by(|&a, &b| {
+                let ratio = |r: usize| tableau[r][cols] / tableau[r][entering];
+                ratio(a).total_cmp(&ratio(b)).then(basis[a].cmp(&basis[b]))
+            })
+            .expect("the x <= 1 rows keep the problem bounded");
+        let pivot = tableau[leaving][entering];
+        for value in &mut tableau[leaving] {
+            *value /= pivot;
+        }
+        let pivot_row = tableau[leaving].clone();
+        for (r, row) in tableau.iter_mut().enumerate() {
+            let factor = row[entering];
+            if r != leaving && factor != 0.0 {
+                for (value, p) in row.iter_mut().zip(&pivot_row) {
+                    *value -= factor * p;
+                }
+            }
+        }
+        let factor = objective[entering];
+        for (value, p) in objective.iter_mut().zip(&pivot_row) {
+            *value -= factor * p;
+        }
+        basis[leaving] = entering;
+    }
+
+    let mut x = vec![0.0; n];
+    for (r, &variable) in basis.iter().enumerate() {
+        if variable < n {
+            x[variable] = tableau[r][cols].clamp(0.0, 1.0);
+        }
+    }
+    (objective[cols], x)
+}
+
+pub(crate) fn plan<M: CostModel>(
+    scheduler: &ResourceAwareScheduler<M>,
+    tasks: &[Task],
+    risk_tolerance: f64,
+    strategy: PlanningStrategy,
+) -> BatchPlan {
+    let (problem, resources) = Problem::new(scheduler, tasks, risk_tolerance);
+    let (mut selected, upper_bound) = match strategy {
+        PlanningStrategy::Exact => {
+            assert!(
+                tasks.len() <= MAX_EXACT_CANDIDATES,
+                "exact planning is limited to {MAX_EXACT_CANDIDATES} candidates"
+            );
+            (problem.exact(), None)
+        }
+        PlanningStrategy::Greedy => (problem.greedy(&problem.by_density()), None),
+        PlanningStrategy::LpRelaxation => {
+            let (selected, bound) = problem.lp_relaxation();
+            (selected, Some(bound))
+        }
+    };
+    selected.sort_unstable();
+
+    let mut expected_usage = ResourceVector::new();
+    for &i in &selected {
+        let mean: ResourceVector = resources
+            .iter()
+            .cloned()
+            .zip(problem.candidates[i].mean.iter().copied())
+            .collect();
+        expected_usage += &mean;
+    }
+    BatchPlan {
+        strategy,
+        value: selected.iter().map(|&i| tasks[i].value).sum(),
+        selected,
+        expected_usage,
+        upper_bound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::Budgets;
+    use crate::uncertainty_quantification::UncertainValue;
+
+    fn task(name: &str, cpu_seconds: f64, cpu_std: f64, value: f64) -> Task {
+        Task {
+            name: name.to_string(),
+            operations: UncertainValue::new(cpu_seconds * 1e9, cpu_std * 1e9),
+            data_size: 0.0,
+            network: false,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_planning() {
+        let budgets = Budgets::new().with(Resource::CPU, 10.5);
+        let mut scheduler = ResourceAwareScheduler::new(budgets);
+        let tasks = vec![
+            task("dense", 6.0, 0.01, 9.0),
+            task("left", 5.0, 0.01, 7.0),
+            task("right", 5.0, 0.01, 7.0),
+            task("idle", 0.1, 0.0, 0.0),
+        ];
+
+        // Greedy takes the densest task first and then nothing else fits.
+        let greedy = scheduler.plan_batch(&tasks, 0.05, PlanningStrategy::Greedy);
+        assert_eq!(greedy.selected, vec![0]);
+        assert_eq!(greedy.value, 9.0);
+
+        // The relaxation can split a task, so its bound is above any plan.
+        let lp = scheduler.plan_batch(&tasks, 0.05, PlanningStrategy::LpRelaxation);
+        let bound = lp.upper_bound.unwrap();
+        assert!((bound - 15.3).abs() < 1e-9, "bound was {bound}");
+        assert!(lp.value <= bound);
+
+        // The two smaller tasks together are worth more, and leave enough
+        // slack that their combined uncertainty stays within the tolerance.
+        let (exact, handles) = scheduler.schedule_batch(&tasks, 0.05, PlanningStrategy::Exact);
+        assert_eq!(exact.selected, vec![1, 2]);
+        assert_eq!(exact.value, 14.0);
+        assert!((exact.expected_usage.get(&Resource::CPU) - 10.0).abs() < 1e-9);
+        assert_eq!(handles.len(), 2);
+        assert!((scheduler.consumed().get(&Resource::CPU) - 10.0).abs() < 1e-9);
+        drop(handles);
+
+        // With more uncertainty, the pair risks overload and is refused.
+        let shaky = vec![task("left", 5.0, 0.5, 7.0), task("right", 5.0, 0.5, 7.0)];
+        let plan = scheduler.plan_batch(&shaky, 0.05, PlanningStrategy::Exact);
+        assert_eq!(plan.selected.len(), 1);
+        let plan = scheduler.plan_batch(&shaky, 0.3, PlanningStrategy::Exact);
+        assert_eq!(plan.selected, vec![0, 1]);
+    }
+}
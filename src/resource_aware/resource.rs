@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::time::Duration;
+
+/// A dimension of resource consumption, such as CPU time or license seats.
+///
+/// The common dimensions are provided as constants. A deployment declares its
+/// own as constants too, e.g. `const DISK_IOPS: Resource = Resource::new("disk_iops");`,
+/// or with `Resource::named` for names only known at runtime.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Resource(Cow<'static, str>);
+
+impl Resource {
+    /// Processor time, in seconds.
+    pub const CPU: Resource = Resource::new("cpu");
+    /// Energy, in joules.
+    pub const ENERGY: Resource = Resource::new("energy");
+    /// Memory, in bytes.
+    pub const MEMORY: Resource = Resource::new("memory");
+    /// Network transfer, in bytes.
+    pub const BANDWIDTH: Resource = Resource::new("bandwidth");
+
+    /// Declares a resource with a static name.
+    pub const fn new(name: &'static str) -> Self {
+        Resource(Cow::Borrowed(name))
+    }
+
+    /// Declares a resource whose name is only known at runtime.
+    pub fn named(name: impl Into<String>) -> Self {
+        Resource(Cow::Owned(name.into()))
+    }
+
+    /// Returns the name of the resource.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// An amount for each of any number of resources. Resources that were never
+/// set count as zero.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ResourceVector {
+    amounts: BTreeMap<Resource, f64>,
+}
+
+impl ResourceVector {
+    /// Creates an empty vector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the amount of `resource`.
+    pub fn with(mut self, resource: Resource, amount: f64) -> Self {
+        self.set(resource, amount);
+        self
+    }
+
+    /// Returns the amount of `resource`, or zero if it was never set.
+    pub fn get(&self, resource: &Resource) -> f64 {
+        self.amounts.get(resource).copied().unwrap_or(0.0)
+    }
+
+    /// Sets the amount of `resource`.
+    pub fn set(&mut self, resource: Resource, amount: f64) {
+        self.amounts.insert(resource, amount);
+    }
+
+    /// Iterates over the resources that were set and their amounts.
+    pub fn iter(&self) -> impl Iterator<Item = (&Resource, f64)> {
+        self.amounts.iter().map(|(r, &a)| (r, a))
+    }
+
+    /// Returns `true` if no resource was set.
+    pub fn is_empty(&self) -> bool {
+        self.amounts.is_empty()
+    }
+}
+
+impl FromIterator<(Resource, f64)> for ResourceVector {
+    fn from_iter<I: IntoIterator<Item = (Resource, f64)>>(iter: I) -> Self {
+        ResourceVector {
+            amounts: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl AddAssign<&ResourceVector> for ResourceVector {
+    fn add_assign(&mut self, other: &ResourceVector) {
+        for (resource, amount) in other.iter() {
+            *self.amounts.entry(resource.clone()).or_insert(0.0) += amount;
+        }
+    }
+}
+
+impl SubAssign<&ResourceVector> for ResourceVector {
+    fn sub_assign(&mut self, other: &ResourceVector) {
+        for (resource, amount) in other.iter() {
+            *self.amounts.entry(resource.clone()).or_insert(0.0) -= amount;
+        }
+    }
+}
+
+impl Add<&ResourceVector> for ResourceVector {
+    type Output = ResourceVector;
+
+    fn add(mut self, other: &ResourceVector) -> ResourceVector {
+        self += other;
+        self
+    }
+}
+
+impl Sub<&ResourceVector> for ResourceVector {
+    type Output = ResourceVector;
+
+    fn sub(mut self, other: &ResourceVector) -> ResourceVector {
+        self -= other;
+        self
+    }
+}
+
+/// Defines the resource budgets for the scheduler.
+///
+/// Only resources given a limit are constrained; tasks may consume any amount
+/// of the others. A plain limit is a capacity: running tasks hold their share
+/// until they finish. A windowed limit is a rate, such as energy per hour:
+/// what finished tasks actually used keeps counting against it until the
+/// window has passed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Budgets {
+    limits: ResourceVector,
+    windows: BTreeMap<Resource, Duration>,
+}
+
+impl Budgets {
+    /// Creates budgets that constrain nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the amount of `resource` held by running tasks to `limit`.
+    pub fn with(mut self, resource: Resource, limit: f64) -> Self {
+        self.windows.remove(&resource);
+        self.limits.set(resource, limit);
+        self
+    }
+
+    /// Limits the use of `resource` to `limit` in any sliding window of
+    /// length `window`.
+    pub fn with_window(mut self, resource: Resource, limit: f64, window: Duration) -> Self {
+        self.windows.insert(resource.clone(), window);
+        self.limits.set(resource, limit);
+        self
+    }
+
+    /// Returns the refill window of `resource`, or `None` if its limit is a
+    /// capacity or it is unconstrained.
+    pub fn window(&self, resource: &Resource) -> Option<Duration> {
+        self.windows.get(resource).copied()
+    }
+
+    pub(crate) fn windows(&self) -> &BTreeMap<Resource, Duration> {
+        &self.windows
+    }
+
+    /// Returns the limit on `resource`, or `None` if it is unconstrained.
+    pub fn limit(&self, resource: &Resource) -> Option<f64> {
+        self.limits.amounts.get(resource).copied()
+    }
+
+    /// Returns the limits of all constrained resources.
+    pub fn limits(&self) -> &ResourceVector {
+        &self.limits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::cost::{CostDriver, LinearCostModel};
+    use crate::resource_aware::{ResourceAwareScheduler, Task};
+    use crate::uncertainty_quantification::UncertainValue;
+
+    const LICENSE_SEATS: Resource = Resource::new("license_seats");
+
+    #[test]
+    fn test_verify_resource_dimensions() {
+        let a = ResourceVector::new()
+            .with(Resource::CPU, 2.0)
+            .with(LICENSE_SEATS, 1.0);
+        let b = ResourceVector::new().with(Resource::CPU, 0.5);
+        let sum = a.clone() + &b;
+        assert_eq!(sum.get(&Resource::CPU), 2.5);
+        assert_eq!(sum.get(&LICENSE_SEATS), 1.0);
+        assert_eq!(
+            sum.get(&Resource::named("gpu")),
+            0.0,
+            "Unset resources are zero"
+        );
+        assert_eq!((sum - &b), a);
+        assert_eq!(Resource::named("license_seats"), LICENSE_SEATS);
+
+        // Two seats and no limit on anything else: the third task is refused
+        // even though it is certain to fit every other dimension.
+        let budgets = Budgets::new().with(LICENSE_SEATS, 2.0);
+        assert_eq!(budgets.limit(&Resource::CPU), None);
+        let model = LinearCostModel::new().with_resource(LICENSE_SEATS, CostDriver::PerTask, 1.0);
+        let mut scheduler = ResourceAwareScheduler::with_cost_model(budgets, model);
+        let task = Task {
+            name: "render".to_string(),
+            operations: UncertainValue::new(1e12, 1e11),
+            data_size: 1e9,
+            network: true,
+            value: 1.0,
+        };
+        assert!(scheduler.schedule_task(&task, 0.05));
+        assert!(scheduler.schedule_task(&task, 0.05));
+        assert!(!scheduler.schedule_task(&task, 0.05));
+        assert_eq!(scheduler.consumed().get(&LICENSE_SEATS), 2.0);
+        assert!((scheduler.consumed().get(&Resource::CPU) - 2_000.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,180 @@
+use super::cost::CostEstimate;
+use super::{Resource, ResourceVector};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How the overload probabilities of individual resources are combined into
+/// the probability compared against the risk tolerance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RiskMode {
+    /// Each resource is checked on its own. The probability of overloading
+    /// some resource can be up to the tolerance times the number of budgets.
+    #[default]
+    PerResource,
+    /// The per-resource probabilities are summed. By the union (Bonferroni)
+    /// bound this limits the probability of any overload, whatever the
+    /// dependence between costs, at the price of being conservative.
+    Bonferroni,
+    /// The probability of any overload is estimated from `samples` joint
+    /// draws of the costs, respecting their correlations. Draws are seeded
+    /// with `seed`, so a decision is reproducible. With no draws there is no
+    /// evidence either way, and an overload is assumed certain.
+    MonteCarlo {
+        /// The number of joint cost draws.
+        samples: usize,
+        /// The seed of the random number generator.
+        seed: u64,
+    },
+}
+
+/// The risk that admitting a task overloads the budgets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverloadRisk {
+    /// The mode the risk was assessed with.
+    pub mode: RiskMode,
+    /// The probability that each budgeted resource the task uses overflows.
+    pub per_resource: BTreeMap<Resource, f64>,
+    /// The probability compared against the risk tolerance: the largest
+    /// per-resource probability for `PerResource`, their sum for
+    /// `Bonferroni`, and the sampled probability of any overload for
+    /// `MonteCarlo`.
+    pub probability: f64,
+}
+
+impl OverloadRisk {
+    /// Returns the resource most likely to overflow, or `None` if no budget
+    /// is at risk.
+    pub fn dominant(&self) -> Option<&Resource> {
+        self.per_resource
+            .iter()
+            .filter(|(_, &p)| p > 0.0)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(resource, _)| resource)
+    }
+
+    /// Returns `true` if the risk is below `risk_tolerance`. A task that
+    /// uses no budgeted resource is always within tolerance.
+    pub fn within(&self, risk_tolerance: f64) -> bool {
+        self.per_resource.is_empty() || self.probability < risk_tolerance
+    }
+}
+
+/// Assesses the risk of a cost given what `remaining` of each budgeted
+/// resource is left. Resources absent from `remaining` are unconstrained.
+pub(crate) fn assess(
+    cost: &CostEstimate,
+    remaining: &ResourceVector,
+    mode: RiskMode,
+) -> OverloadRisk {
+    let per_resource: BTreeMap<Resource, f64> = remaining
+        .iter()
+        .filter_map(|(resource, left)| {
+            let distribution = cost.get(resource)?;
+            Some((resource.clone(), 1.0 - distribution.cdf(left)))
+        })
+        .collect();
+    let probability = match mode {
+        RiskMode::PerResource => per_resource.values().copied().fold(0.0, f64::max),
+        RiskMode::Bonferroni => per_resource.values().sum::<f64>().min(1.0),
+        RiskMode::MonteCarlo { samples: 0, .. } => 1.0,
+        RiskMode::MonteCarlo { samples, seed } => {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let overloads = (0..samples)
+                .filter(|_| {
+                    let draw = cost.sample(&mut rng);
+                    per_resource
+                        .keys()
+                        .any(|resource| draw.get(resource) > remaining.get(resource))
+                })
+                .count();
+            overloads as f64 / samples as f64
+        }
+    };
+    OverloadRisk {
+        mode,
+        per_resource,
+        probability,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::cost::{CostModel, LinearCostModel};
+    use crate::resource_aware::{Budgets, ResourceAwareScheduler, Task};
+    use crate::uncertainty_quantification::UncertainValue;
+
+    /// The default model with the correlation between costs removed.
+    struct Independent(LinearCostModel);
+
+    impl CostModel for Independent {
+        fn estimate(&self, task: &Task) -> CostEstimate {
+            self.0
+                .estimate(task)
+                .iter()
+                .fold(CostEstimate::new(), |estimate, (resource, cost)| {
+                    estimate.with(resource.clone(), cost.clone())
+                })
+        }
+    }
+
+    #[test]
+    fn test_verify_joint_risk() {
+        // 4 ± 1 seconds of CPU and 4 ± 1 joules. Each budget alone overflows
+        // with 4.5% and 3.6% probability respectively.
+        let task = Task {
+            name: "transcode".to_string(),
+            operations: UncertainValue::new(4e9, 1e9),
+            data_size: 0.0,
+            network: false,
+            value: 1.0,
+        };
+        let budgets = Budgets::new()
+            .with(Resource::CPU, 5.7)
+            .with(Resource::ENERGY, 5.8);
+        let monte_carlo = RiskMode::MonteCarlo {
+            samples: 20_000,
+            seed: 7,
+        };
+
+        let scheduler = ResourceAwareScheduler::new(budgets.clone());
+        let risk = scheduler.overload_risk(&task);
+        assert_eq!(risk.dominant(), Some(&Resource::CPU));
+        assert!((risk.per_resource[&Resource::CPU] - 0.0446).abs() < 1e-3);
+        assert!(scheduler.can_schedule(&task, 0.05));
+
+        // The union bound counts both budgets and refuses.
+        let scheduler = scheduler.with_risk_mode(RiskMode::Bonferroni);
+        assert!((scheduler.overload_risk(&task).probability - 0.0805).abs() < 1e-3);
+        assert!(!scheduler.can_schedule(&task, 0.05));
+
+        // CPU time and energy grow with the same operation count, so they
+        // overflow together and the joint risk is barely above the CPU's.
+        let scheduler = scheduler.with_risk_mode(monte_carlo);
+        let joint = scheduler.overload_risk(&task).probability;
+        assert!((joint - 0.0446).abs() < 0.005, "joint risk {joint}");
+        assert!(scheduler.can_schedule(&task, 0.05));
+
+        // Were they independent, both overflows would add up.
+        let scheduler =
+            ResourceAwareScheduler::with_cost_model(budgets, Independent(LinearCostModel::new()))
+                .with_risk_mode(monte_carlo);
+        let joint = scheduler.overload_risk(&task).probability;
+        assert!((joint - 0.0789).abs() < 0.005, "joint risk {joint}");
+        assert!(!scheduler.can_schedule(&task, 0.05));
+
+        // Without samples nothing is known, so nothing is admitted.
+        let scheduler = scheduler.with_risk_mode(RiskMode::MonteCarlo {
+            samples: 0,
+            seed: 7,
+        });
+        assert_eq!(scheduler.overload_risk(&task).probability, 1.0);
+        let tiny = Task {
+            operations: UncertainValue::new(1e6, 0.0),
+            ..task
+        };
+        assert!(!scheduler.can_schedule(&tiny, 0.05));
+    }
+}
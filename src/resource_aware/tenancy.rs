@@ -0,0 +1,315 @@
+use super::accounting::{lock, Ledger};
+use super::cost::{CostEstimate, CostModel};
+use super::decision::ScheduleDecision;
+use super::{risk, Resource, ResourceAwareScheduler, ResourceVector, Task};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A tenant's share of a scheduler's budgets.
+///
+/// Capacity is split in two tiers. Guarantees are set aside for the tenant
+/// and never lent to anyone else. What is left over is divided among all
+/// tenants in proportion to their weights. A tenant's guarantee plus its
+/// weighted share of the rest is its entitlement. A tenant may go beyond its
+/// entitlement by borrowing idle capacity, up to an optional hard limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quota {
+    weight: f64,
+    guarantees: ResourceVector,
+    limits: BTreeMap<Resource, f64>,
+}
+
+impl Quota {
+    /// Creates a quota with no guarantees or limits, sharing capacity with
+    /// relative weight `weight`.
+    pub fn new(weight: f64) -> Self {
+        Quota {
+            weight: weight.max(0.0),
+            guarantees: ResourceVector::new(),
+            limits: BTreeMap::new(),
+        }
+    }
+
+    /// Sets aside `amount` of `resource` for the tenant alone.
+    pub fn with_guarantee(mut self, resource: Resource, amount: f64) -> Self {
+        self.guarantees.set(resource, amount);
+        self
+    }
+
+    /// Caps the tenant's use of `resource` at `limit`, borrowing included.
+    pub fn with_limit(mut self, resource: Resource, limit: f64) -> Self {
+        self.limits.insert(resource, limit);
+        self
+    }
+
+    /// Returns the tenant's relative weight.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Returns the amount of `resource` guaranteed to the tenant.
+    pub fn guarantee(&self, resource: &Resource) -> f64 {
+        self.guarantees.get(resource)
+    }
+
+    /// Returns the cap on the tenant's use of `resource`, if any.
+    pub fn limit(&self, resource: &Resource) -> Option<f64> {
+        self.limits.get(resource).copied()
+    }
+}
+
+/// Why a tenant's task was admitted or rejected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantDecision {
+    /// The tenant the task was submitted for.
+    pub tenant: String,
+    /// The tenant's guarantee plus weighted fair share of each budget.
+    pub entitlement: ResourceVector,
+    /// What is committed to the tenant: the expected cost of its running
+    /// tasks plus, for windowed budgets, what its completed tasks used within
+    /// the window.
+    pub usage: ResourceVector,
+    /// Whether the task would take the tenant beyond its entitlement.
+    pub borrowing: bool,
+    /// The admission decision. Its checks measure what this tenant may use:
+    /// their `committed` amounts include capacity held back for other
+    /// tenants.
+    pub decision: ScheduleDecision,
+}
+
+/// Returns the entitlement of `tenant` to every budgeted resource.
+pub(crate) fn entitlement<M>(
+    scheduler: &ResourceAwareScheduler<M>,
+    tenant: &str,
+) -> ResourceVector {
+    scheduler
+        .budgets
+        .limits()
+        .iter()
+        .map(|(resource, limit)| {
+            (
+                resource.clone(),
+                entitled(scheduler, tenant, resource, limit),
+            )
+        })
+        .collect()
+}
+
+fn entitled<M>(
+    scheduler: &ResourceAwareScheduler<M>,
+    tenant: &str,
+    resource: &Resource,
+    limit: f64,
+) -> f64 {
+    let Some(quota) = scheduler.tenants.get(tenant) else {
+        return 0.0;
+    };
+    let guaranteed: f64 = scheduler
+        .tenants
+        .values()
+        .map(|q| q.guarantee(resource))
+        .sum();
+    let pool = (limit - guaranteed).max(0.0);
+    quota.guarantee(resource) + pool * weight_share(scheduler, tenant)
+}
+
+fn weight_share<M>(scheduler: &ResourceAwareScheduler<M>, tenant: &str) -> f64 {
+    let total: f64 = scheduler.tenants.values().map(Quota::weight).sum();
+    match scheduler.tenants.get(tenant) {
+        Some(quota) if total > 0.0 => quota.weight / total,
+        _ => 0.0,
+    }
+}
+
+/// Returns how much of `resource` is set aside for tenants other than
+/// `tenant`: the part of each one's guarantee it has not committed.
+pub(crate) fn protected<M>(
+    scheduler: &ResourceAwareScheduler<M>,
+    ledger: &mut Ledger,
+    resource: &Resource,
+    tenant: Option<&str>,
+) -> f64 {
+    scheduler
+        .tenants
+        .iter()
+        .filter(|(name, _)| Some(name.as_str()) != tenant)
+        .map(|(name, quota)| {
+            (quota.guarantee(resource) - ledger.tenant_committed(name, resource)).max(0.0)
+        })
+        .sum()
+}
+
+/// Explains whether `tenant` may run a task of cost `cost` now.
+///
+/// For each budget, the tenant may use what remains of its entitlement plus
+/// its weighted share of the idle capacity beyond that, where idle capacity
+/// excludes other tenants' unused guarantees. The overload-probability check
+/// then runs against the smaller of that allowance, the idle capacity, and
+/// the tenant's limit.
+pub(crate) fn explain<M: CostModel>(
+    scheduler: &ResourceAwareScheduler<M>,
+    tenant: &str,
+    task: &Task,
+    cost: &CostEstimate,
+    risk_tolerance: f64,
+) -> TenantDecision {
+    let entitlement = entitlement(scheduler, tenant);
+    let share = weight_share(scheduler, tenant);
+    let quota = scheduler.tenants.get(tenant);
+    let (usage, remaining) = {
+        let mut ledger = lock(&scheduler.ledger);
+        let usage = ledger.tenant_committed_all(tenant);
+        let remaining: ResourceVector = cost
+            .iter()
+            .filter_map(|(resource, _)| {
+                let limit = scheduler.budgets.limit(resource)?;
+                let protected = protected(scheduler, &mut ledger, resource, Some(tenant));
+                let idle = limit - ledger.committed(resource) - protected;
+                let own = (entitlement.get(resource) - usage.get(resource)).max(0.0);
+                let allowance = own + share * (idle - own).max(0.0);
+                let cap = quota
+                    .and_then(|q| q.limit(resource))
+                    .map_or(f64::INFINITY, |cap| cap - usage.get(resource));
+                Some((resource.clone(), idle.min(allowance).min(cap)))
+            })
+            .collect();
+        (usage, remaining)
+    };
+    let expected = cost.mean();
+    let borrowing = remaining.iter().any(|(resource, _)| {
+        usage.get(resource) + expected.get(resource) > entitlement.get(resource)
+    });
+    let risk = risk::assess(cost, &remaining, scheduler.risk_mode);
+    let decision = ScheduleDecision::new(
+        &task.name,
+        &scheduler.budgets,
+        cost,
+        &remaining,
+        risk,
+        risk_tolerance,
+    );
+    TenantDecision {
+        tenant: tenant.to_string(),
+        entitlement,
+        usage,
+        borrowing,
+        decision,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::planner::PlanningStrategy;
+    use crate::resource_aware::Budgets;
+    use crate::time_aware::clock::VirtualClock;
+    use crate::uncertainty_quantification::UncertainValue;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn job(name: &str) -> Task {
+        Task {
+            name: name.to_string(),
+            operations: UncertainValue::new(0.9e9, 0.0),
+            data_size: 0.0,
+            network: false,
+            value: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_verify_fair_share_quotas() {
+        // 12s of CPU. Search is guaranteed 2s and weighs twice as much as
+        // batch, so of the 10s left it is entitled to two thirds.
+        let mut scheduler = ResourceAwareScheduler::new(Budgets::new().with(Resource::CPU, 12.0))
+            .with_tenant("search", Quota::new(2.0).with_guarantee(Resource::CPU, 2.0))
+            .with_tenant("batch", Quota::new(1.0));
+        let search = scheduler.entitlement("search").get(&Resource::CPU);
+        let batch = scheduler.entitlement("batch").get(&Resource::CPU);
+        assert!((search - (2.0 + 20.0 / 3.0)).abs() < 1e-9);
+        assert!((batch - 10.0 / 3.0).abs() < 1e-9);
+
+        // With search idle, batch borrows beyond its 3.3s, taking a third of
+        // the idle capacity each time, and never touching search's guarantee.
+        let mut batch_jobs = Vec::new();
+        loop {
+            let (decision, handle) = scheduler.schedule_for("batch", &job("etl"), 0.05);
+            let Some(handle) = handle else {
+                assert!(decision.borrowing);
+                assert_eq!(decision.decision.binding, Some(Resource::CPU));
+                break;
+            };
+            assert_eq!(handle.tenant(), Some("batch"));
+            batch_jobs.push(handle);
+        }
+        let used = scheduler.tenant_usage("batch").get(&Resource::CPU);
+        assert_eq!(batch_jobs.len(), 9);
+        assert!(used > batch && used <= 10.0, "batch used {used}");
+
+        // Search still gets its guarantee and more.
+        let search_jobs: Vec<_> = (0..4)
+            .map_while(|_| scheduler.schedule_for("search", &job("query"), 0.05).1)
+            .collect();
+        assert_eq!(search_jobs.len(), 4);
+        assert!((scheduler.consumed().get(&Resource::CPU) - 11.7).abs() < 1e-9);
+
+        // Finishing batch work returns the capacity to the pool.
+        drop(batch_jobs);
+        assert!(scheduler.tenant_usage("batch").get(&Resource::CPU).abs() < 1e-9);
+        let unknown = scheduler.explain_for("adhoc", &job("scan"), 0.05);
+        assert!(!unknown.decision.admitted, "Unknown tenants have no share");
+
+        // A hard limit stops borrowing.
+        let mut capped = ResourceAwareScheduler::new(Budgets::new().with(Resource::CPU, 12.0))
+            .with_tenant("batch", Quota::new(1.0).with_limit(Resource::CPU, 2.0));
+        let admitted: Vec<_> = (0..5)
+            .map_while(|_| capped.schedule_for("batch", &job("etl"), 0.05).1)
+            .collect();
+        assert_eq!(admitted.len(), 2);
+        drop(search_jobs);
+
+        // Untenanted work may not take a tenant's unused guarantee: of 12s,
+        // 10s are open to it, and search can still use its 2s.
+        let mut shared = ResourceAwareScheduler::new(Budgets::new().with(Resource::CPU, 12.0))
+            .with_tenant("search", Quota::new(1.0).with_guarantee(Resource::CPU, 2.0));
+        let untenanted: Vec<_> = (0..13)
+            .map_while(|_| shared.schedule(&job("cron"), 0.05))
+            .collect();
+        assert_eq!(untenanted.len(), 11);
+        let decision = shared.explain(&job("cron"), 0.05);
+        assert!((decision.resources[&Resource::CPU].headroom + 0.8).abs() < 1e-9);
+        let batch: Vec<Task> = (0..3).map(|_| job("cron")).collect();
+        assert!(shared
+            .plan_batch(&batch, 0.05, PlanningStrategy::Exact)
+            .selected
+            .is_empty());
+        let search_jobs: Vec<_> = (0..2)
+            .map_while(|_| shared.schedule_for("search", &job("query"), 0.05).1)
+            .collect();
+        assert_eq!(search_jobs.len(), 2);
+        drop((untenanted, search_jobs));
+
+        // Within a window, what completed tasks used still counts against
+        // their tenant.
+        let clock = Arc::new(VirtualClock::new());
+        let hourly = Budgets::new().with_window(Resource::ENERGY, 12.0, Duration::from_secs(3600));
+        let mut metered = ResourceAwareScheduler::new(hourly)
+            .with_clock(clock.clone())
+            .with_tenant("batch", Quota::new(1.0).with_limit(Resource::ENERGY, 2.0));
+        let used = ResourceVector::new().with(Resource::ENERGY, 0.9);
+        for _ in 0..2 {
+            let (_, handle) = metered.schedule_for("batch", &job("etl"), 0.05);
+            handle.unwrap().complete(&used);
+        }
+        assert!((metered.tenant_usage("batch").get(&Resource::ENERGY) - 1.8).abs() < 1e-9);
+        let (decision, handle) = metered.schedule_for("batch", &job("etl"), 0.05);
+        assert!(
+            handle.is_none(),
+            "Only 0.2J of the 2J cap is left this hour"
+        );
+        assert_eq!(decision.decision.binding, Some(Resource::ENERGY));
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(metered.tenant_usage("batch").get(&Resource::ENERGY), 0.0);
+        assert!(metered.schedule_for("batch", &job("etl"), 0.05).1.is_some());
+    }
+}
@@ -0,0 +1,349 @@
+use super::accounting::TaskHandle;
+use super::cost::CostModel;
+use super::decision::ScheduleDecision;
+use super::{Resource, ResourceAwareScheduler, Task};
+use crate::uncertainty_quantification::UncertainValue;
+use statrs::distribution::{ContinuousCDF, Normal};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// A set of tasks with precedence constraints: a directed acyclic graph in
+/// which an edge from `a` to `b` means `b` may only start once `a` is done.
+///
+/// Tasks are identified by the index `add_task` returns.
+#[derive(Default)]
+pub struct Workflow {
+    tasks: Vec<Task>,
+    predecessors: Vec<Vec<usize>>,
+    successors: Vec<Vec<usize>>,
+}
+
+impl Workflow {
+    /// Creates an empty workflow.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a task with no dependencies and returns its index.
+    pub fn add_task(&mut self, task: Task) -> usize {
+        self.tasks.push(task);
+        self.predecessors.push(Vec::new());
+        self.successors.push(Vec::new());
+        self.tasks.len() - 1
+    }
+
+    /// Makes `after` depend on `before`. Returns `false`, leaving the
+    /// workflow unchanged, if either index is out of range or the edge
+    /// would create a cycle.
+    pub fn add_dependency(&mut self, before: usize, after: usize) -> bool {
+        if before >= self.len() || after >= self.len() || self.reaches(after, before) {
+            return false;
+        }
+        if !self.successors[before].contains(&after) {
+            self.successors[before].push(after);
+            self.predecessors[after].push(before);
+        }
+        true
+    }
+
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut visited = vec![false; self.len()];
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+            if !std::mem::replace(&mut visited[node], true) {
+                stack.extend(&self.successors[node]);
+            }
+        }
+        false
+    }
+
+    /// Returns the number of tasks.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if the workflow has no tasks.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Returns the task at `index`.
+    pub fn task(&self, index: usize) -> Option<&Task> {
+        self.tasks.get(index)
+    }
+
+    /// Returns the tasks that must finish before the task at `index` starts.
+    pub fn dependencies(&self, index: usize) -> Option<&[usize]> {
+        self.predecessors.get(index).map(Vec::as_slice)
+    }
+
+    /// Returns the tasks in an order that respects every dependency,
+    /// breaking ties by index.
+    pub fn topological_order(&self) -> Vec<usize> {
+        let mut pending: Vec<usize> = self.predecessors.iter().map(Vec::len).collect();
+        let mut ready: BTreeSet<usize> = (0..self.len()).filter(|&i| pending[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(node) = ready.pop_first() {
+            order.push(node);
+            for &next in &self.successors[node] {
+                pending[next] -= 1;
+                if pending[next] == 0 {
+                    ready.insert(next);
+                }
+            }
+        }
+        order
+    }
+
+    /// Plans the workflow with durations taken from the CPU time
+    /// `cost_model` estimates for each task, assuming independent tasks run
+    /// in parallel as soon as their dependencies finish.
+    ///
+    /// The critical path is built backwards from the latest-finishing task,
+    /// choosing at each step the predecessor whose path has the latest
+    /// finish at the requested `confidence`. Its durations are summed as
+    /// independent `UncertainValue`s.
+    pub fn plan<M: CostModel>(
+        &self,
+        cost_model: &M,
+        deadline: Duration,
+        confidence: f64,
+    ) -> WorkflowPlan {
+        let z = if confidence > 0.0 && confidence < 1.0 {
+            Normal::new(0.0, 1.0)
+                .expect("valid parameters")
+                .inverse_cdf(confidence)
+        } else {
+            0.0
+        };
+        let quantile = |d: &UncertainValue| d.mean + z * d.std_dev;
+        let durations: Vec<UncertainValue> = self
+            .tasks
+            .iter()
+            .map(|task| {
+                let estimate = cost_model.estimate(task);
+                estimate
+                    .get(&Resource::CPU)
+                    .map_or(UncertainValue::new(0.0, 0.0), |cpu| {
+                        UncertainValue::new(cpu.mean(), cpu.std_dev())
+                    })
+            })
+            .collect();
+
+        let order = self.topological_order();
+        let mut earliest_start = vec![0.0; self.len()];
+        let mut finish = durations.clone();
+        let mut via: Vec<Option<usize>> = vec![None; self.len()];
+        for &node in &order {
+            for &before in &self.predecessors[node] {
+                earliest_start[node] = f64::max(
+                    earliest_start[node],
+                    earliest_start[before] + durations[before].mean,
+                );
+                let candidate = finish[before].add(&durations[node]);
+                if via[node].is_none() || quantile(&candidate) > quantile(&finish[node]) {
+                    finish[node] = candidate;
+                    via[node] = Some(before);
+                }
+            }
+        }
+
+        let last =
+            (0..self.len()).max_by(|&a, &b| quantile(&finish[a]).total_cmp(&quantile(&finish[b])));
+        let mut critical_path: Vec<usize> =
+            std::iter::successors(last, |&node| via[node]).collect();
+        critical_path.reverse();
+        let makespan = last.map_or(UncertainValue::new(0.0, 0.0), |node| finish[node]);
+        let seconds = deadline.as_secs_f64();
+        let deadline_probability = if makespan.std_dev == 0.0 {
+            if makespan.mean <= seconds {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            makespan.confidence(seconds)
+        };
+        WorkflowPlan {
+            order,
+            earliest_start,
+            critical_path,
+            makespan,
+            deadline,
+            confidence,
+            deadline_probability,
+        }
+    }
+}
+
+/// The schedule of a [`Workflow`] and its chance of meeting a deadline.
+#[derive(Debug, Clone)]
+pub struct WorkflowPlan {
+    /// The order tasks are admitted in, which respects every dependency.
+    pub order: Vec<usize>,
+    /// The expected start time of each task, in seconds from the start of
+    /// the workflow, indexed by task.
+    pub earliest_start: Vec<f64>,
+    /// The tasks on the critical path, first to last.
+    pub critical_path: Vec<usize>,
+    /// The duration of the critical path, in seconds.
+    pub makespan: UncertainValue,
+    /// The deadline the workflow was planned against.
+    pub deadline: Duration,
+    /// The requested probability of meeting the deadline.
+    pub confidence: f64,
+    /// The probability that the critical path finishes by the deadline.
+    pub deadline_probability: f64,
+}
+
+impl WorkflowPlan {
+    /// Returns `true` if the critical path meets the deadline with at least
+    /// the requested confidence.
+    pub fn meets_deadline(&self) -> bool {
+        self.deadline_probability >= self.confidence
+    }
+}
+
+/// Whether a workflow was admitted.
+pub enum WorkflowAdmission {
+    /// Every task was admitted. The handles are in the plan's order.
+    Admitted(Vec<TaskHandle>),
+    /// The critical path is too likely to miss the deadline; nothing was
+    /// reserved.
+    MissesDeadline,
+    /// A task did not fit the budgets, as explained by its decision. Any
+    /// reservations already made for the workflow were released.
+    OverBudget(ScheduleDecision),
+}
+
+/// Plans `workflow` and, if it meets its deadline, admits all of its tasks
+/// or none of them, on behalf of `tenant` if one is given.
+pub(crate) fn schedule<M: CostModel>(
+    scheduler: &mut ResourceAwareScheduler<M>,
+    tenant: Option<&str>,
+    workflow: &Workflow,
+    deadline: Duration,
+    confidence: f64,
+    risk_tolerance: f64,
+) -> (WorkflowPlan, WorkflowAdmission) {
+    let plan = workflow.plan(scheduler.cost_model(), deadline, confidence);
+    if !plan.meets_deadline() {
+        return (plan, WorkflowAdmission::MissesDeadline);
+    }
+    let mut handles = Vec::with_capacity(plan.order.len());
+    for &index in &plan.order {
+        let task = &workflow.tasks[index];
+        let (decision, handle) = match tenant {
+            Some(tenant) => {
+                let (decision, handle) = scheduler.schedule_for(tenant, task, risk_tolerance);
+                (decision.decision, handle)
+            }
+            None => scheduler.schedule_explained(task, risk_tolerance),
+        };
+        match handle {
+            Some(handle) => handles.push(handle),
+            // Dropping the handles releases what was reserved so far.
+            None => return (plan, WorkflowAdmission::OverBudget(decision)),
+        }
+    }
+    (plan, WorkflowAdmission::Admitted(handles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_aware::tenancy::Quota;
+    use crate::resource_aware::Budgets;
+
+    fn task(name: &str, cpu_seconds: f64, cpu_std: f64) -> Task {
+        Task {
+            name: name.to_string(),
+            operations: UncertainValue::new(cpu_seconds * 1e9, cpu_std * 1e9),
+            data_size: 0.0,
+            network: false,
+            value: 1.0,
+        }
+    }
+
+    fn pipeline() -> Workflow {
+        // fetch -> {decode, thumbnail} -> publish
+        let mut workflow = Workflow::new();
+        let fetch = workflow.add_task(task("fetch", 1.0, 0.1));
+        let decode = workflow.add_task(task("decode", 2.0, 0.5));
+        let thumbnail = workflow.add_task(task("thumbnail", 0.5, 0.1));
+        let publish = workflow.add_task(task("publish", 1.0, 0.1));
+        for (before, after) in [
+            (fetch, decode),
+            (fetch, thumbnail),
+            (decode, publish),
+            (thumbnail, publish),
+        ] {
+            assert!(workflow.add_dependency(before, after));
+        }
+        workflow
+    }
+
+    #[test]
+    fn test_verify_workflow_scheduling() {
+        let mut workflow = pipeline();
+        assert!(!workflow.add_dependency(3, 0), "Cycles are refused");
+        assert_eq!(workflow.topological_order(), vec![0, 1, 2, 3]);
+        assert_eq!(workflow.dependencies(3), Some(&[1, 2][..]));
+        assert_eq!(workflow.dependencies(4), None);
+
+        // The critical path runs through decode: 4 ± 0.52 seconds, which
+        // finishes within 5 seconds 97% of the time.
+        let scheduler = ResourceAwareScheduler::new(Budgets::new().with(Resource::CPU, 10.0));
+        let five = Duration::from_secs(5);
+        let plan = workflow.plan(scheduler.cost_model(), five, 0.95);
+        assert_eq!(plan.critical_path, vec![0, 1, 3]);
+        assert!((plan.makespan.mean - 4.0).abs() < 1e-9);
+        assert!((plan.makespan.std_dev - 0.27f64.sqrt()).abs() < 1e-9);
+        assert!((plan.earliest_start[3] - 3.0).abs() < 1e-9);
+        assert!((plan.deadline_probability - 0.973).abs() < 1e-3);
+        assert!(plan.meets_deadline());
+        assert!(!workflow
+            .plan(scheduler.cost_model(), five, 0.99)
+            .meets_deadline());
+
+        // Admission is all or nothing.
+        let mut scheduler = scheduler;
+        let (_, admission) = scheduler.schedule_workflow(&workflow, five, 0.95, 0.05);
+        let WorkflowAdmission::Admitted(handles) = admission else {
+            panic!("The workflow fits");
+        };
+        assert_eq!(handles.len(), 4);
+        assert!((scheduler.consumed().get(&Resource::CPU) - 4.5).abs() < 1e-9);
+        drop(handles);
+
+        let mut tight = ResourceAwareScheduler::new(Budgets::new().with(Resource::CPU, 4.0));
+        let (_, admission) = tight.schedule_workflow(&workflow, five, 0.95, 0.05);
+        let WorkflowAdmission::OverBudget(decision) = admission else {
+            panic!("The workflow needs 4.5s of CPU");
+        };
+        assert_eq!(decision.task, "publish");
+        assert_eq!(decision.binding, Some(Resource::CPU));
+        assert!(tight.consumed().get(&Resource::CPU).abs() < 1e-9);
+        let (_, admission) = tight.schedule_workflow(&workflow, five, 0.99, 0.05);
+        assert!(matches!(admission, WorkflowAdmission::MissesDeadline));
+
+        // A tenant's workflow is held to its quota and charged to it.
+        let mut shared = ResourceAwareScheduler::new(Budgets::new().with(Resource::CPU, 10.0))
+            .with_tenant("media", Quota::new(1.0).with_limit(Resource::CPU, 4.0))
+            .with_tenant("search", Quota::new(1.0));
+        let (_, admission) = shared.schedule_workflow_for("media", &workflow, five, 0.95, 0.05);
+        assert!(matches!(admission, WorkflowAdmission::OverBudget(_)));
+        assert!(shared.tenant_usage("media").get(&Resource::CPU).abs() < 1e-9);
+        let (_, admission) = shared.schedule_workflow_for("search", &workflow, five, 0.95, 0.05);
+        let WorkflowAdmission::Admitted(handles) = admission else {
+            panic!("The workflow fits search's share");
+        };
+        assert!((shared.tenant_usage("search").get(&Resource::CPU) - 4.5).abs() < 1e-9);
+        assert!(handles
+            .iter()
+            .all(|handle| handle.tenant() == Some("search")));
+    }
+}
@@ -1,103 +1,363 @@
-use std::time::{Duration, Instant};
+pub mod anytime;
+pub mod clock;
+pub mod criticality;
+pub mod deadline;
+pub mod imprecise;
+pub mod metrics;
+pub mod parallel;
+pub mod profile;
+pub mod scheduling;
+pub mod search;
+pub mod sorting;
+pub mod wcet;
+
+pub use wcet::WcetAnalyzer;
+
+use clock::{Clock, MonotonicClock};
+use metrics::TimeAwareMetrics;
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The outcome of a deadline-bounded sort, describing how far the sort got.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnytimeResult {
+    /// The time spent sorting, as measured by the sorter's clock.
+    pub elapsed: Duration,
+    /// Whether the sort ran to completion before the deadline.
+    pub completed: bool,
+    /// The number of partitioning passes that completed, each of which fixed one
+    /// pivot at its final sorted position.
+    pub finalized_partitions: usize,
+    /// The fraction of element pairs that are in order, from `0.0` (reverse sorted)
+    /// to `1.0` (fully sorted), i.e. `1 - inversions / (n * (n - 1) / 2)`.
+    pub sortedness: f64,
+}
+
+/// An in-progress Lomuto partition pass over `lo..hi`, pivoting on `hi - 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PartitionState {
+    lo: usize,
+    hi: usize,
+    /// The boundary of the "less than pivot" prefix.
+    i: usize,
+    /// The next element to compare against the pivot.
+    j: usize,
+}
+
+/// The saved progress of an interrupted `AnytimeQuicksort`, including any
+/// partition pass that was cut off half-way.
+///
+/// A checkpoint is tied to the slice it was created for: resuming it against a
+/// slice of a different length panics, and modifying the slice between calls
+/// voids the guarantee that the sort finishes correctly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortCheckpoint {
+    len: usize,
+    pending: Vec<(usize, usize)>,
+    active: Option<PartitionState>,
+    finalized_partitions: usize,
+}
+
+impl SortCheckpoint {
+    /// Creates a checkpoint for sorting a slice of `len` elements from scratch.
+    pub fn new(len: usize) -> Self {
+        SortCheckpoint {
+            len,
+            pending: if len > 1 { vec![(0, len)] } else { Vec::new() },
+            active: None,
+            finalized_partitions: 0,
+        }
+    }
+
+    /// Returns `true` once there is no work left to do.
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty() && self.active.is_none()
+    }
+
+    /// Returns the number of elements whose final position is not yet known.
+    pub fn remaining(&self) -> usize {
+        let active = self.active.map_or(0, |p| p.hi - p.lo);
+        active + self.pending.iter().map(|(lo, hi)| hi - lo).sum::<usize>()
+    }
+
+    /// Returns the number of partitioning passes finished so far across all runs.
+    pub fn finalized_partitions(&self) -> usize {
+        self.finalized_partitions
+    }
+}
 
 /// A quicksort implementation that adheres to a strict deadline.
 /// If the deadline is exceeded, the sorting process is halted,
 /// resulting in a partially sorted array.
-pub struct AnytimeQuicksort {
+///
+/// Every swap performed by the partitioning step exchanges an inverted pair, so
+/// the number of inversions never increases: stopping earlier never yields a
+/// worse answer than stopping later.
+///
+/// Pending partitions live on an explicit work stack rather than the call
+/// stack, so an interrupted sort can be saved as a [`SortCheckpoint`] and
+/// finished later with [`AnytimeQuicksort::resume`], e.g. a few milliseconds
+/// per rendered frame.
+///
+/// Deadlines are checked against a [`Clock`], the real monotonic clock by
+/// default; a [`clock::VirtualClock`] makes the cutoff point reproducible.
+///
+/// The Lomuto partition degrades to O(n²) on sorted input; see
+/// [`sorting::AnytimeIntrosort`] and [`sorting::AnytimeMergesort`] for
+/// variants with an O(n log n) worst case.
+pub struct AnytimeQuicksort<C = MonotonicClock> {
     deadline: Duration,
-    start_time: Option<Instant>,
+    clock: C,
+    start_time: Option<Duration>,
+    metrics: Option<Arc<TimeAwareMetrics>>,
 }
 
 impl AnytimeQuicksort {
     /// Creates a new `AnytimeQuicksort` with a specified deadline in milliseconds.
     pub fn new(deadline_ms: u64) -> Self {
+        Self::with_clock(Duration::from_millis(deadline_ms), MonotonicClock::new())
+    }
+}
+
+impl<C: Clock> AnytimeQuicksort<C> {
+    /// Creates a new `AnytimeQuicksort` that measures its deadline with `clock`.
+    pub fn with_clock(deadline: Duration, clock: C) -> Self {
         AnytimeQuicksort {
-            deadline: Duration::from_millis(deadline_ms),
+            deadline,
+            clock,
             start_time: None,
+            metrics: None,
         }
     }
 
+    /// Records every run, including each resumed run, in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<TimeAwareMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Sorts the given array until the deadline is met.
-    pub fn sort(&mut self, arr: &mut [i32]) {
-        self.start_time = Some(Instant::now());
-        self.recursive_sort(arr, 0, (arr.len() as isize) - 1);
+    pub fn sort<T: Ord>(&mut self, arr: &mut [T]) -> AnytimeResult {
+        self.sort_by(arr, T::cmp)
     }
 
-    fn time_exceeded(&self) -> bool {
-        if let Some(start_time) = self.start_time {
-            start_time.elapsed() >= self.deadline
-        } else {
-            false
-        }
+    /// Sorts the given array with a comparator function until the deadline is met.
+    ///
+    /// The sortedness score is computed after the deadline check, so it adds an
+    /// `O(n log n)` pass on top of the budget whenever the sort is interrupted.
+    pub fn sort_by<T, F>(&mut self, arr: &mut [T], compare: F) -> AnytimeResult
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut checkpoint = SortCheckpoint::new(arr.len());
+        self.resume_by(arr, &mut checkpoint, compare)
     }
 
-    fn recursive_sort(&self, arr: &mut [i32], lo: isize, hi: isize) {
-        if self.time_exceeded() {
-            return;
-        }
+    /// Continues a sort from `checkpoint` with a fresh deadline, updating the
+    /// checkpoint in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arr` is not the same length as the slice the checkpoint was
+    /// created for.
+    pub fn resume<T: Ord>(
+        &mut self,
+        arr: &mut [T],
+        checkpoint: &mut SortCheckpoint,
+    ) -> AnytimeResult {
+        self.resume_by(arr, checkpoint, T::cmp)
+    }
+
+    /// Continues a sort from `checkpoint` with a comparator function and a fresh
+    /// deadline. `finalized_partitions` in the result counts passes from all runs,
+    /// while `elapsed` covers this run only.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arr` is not the same length as the slice the checkpoint was
+    /// created for.
+    pub fn resume_by<T, F>(
+        &mut self,
+        arr: &mut [T],
+        checkpoint: &mut SortCheckpoint,
+        mut compare: F,
+    ) -> AnytimeResult
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        assert_eq!(
+            arr.len(),
+            checkpoint.len,
+            "checkpoint was created for a slice of a different length"
+        );
+        let start_time = self.clock.now();
+        self.start_time = Some(start_time);
 
-        if lo >= hi {
-            return;
+        let fired = self.run(arr, checkpoint, &mut compare);
+        let elapsed = self.clock.now().saturating_sub(start_time);
+        let completed = checkpoint.is_complete();
+        let sortedness = if completed {
+            1.0
+        } else {
+            sortedness(arr, &mut compare)
+        };
+
+        let result = AnytimeResult {
+            elapsed,
+            completed,
+            finalized_partitions: checkpoint.finalized_partitions,
+            sortedness,
+        };
+        if let Some(metrics) = &self.metrics {
+            // The deadline can fire on the last pass, after the work ran out.
+            match fired {
+                Some(fired) if !completed => metrics.record_cutoff(self.deadline, elapsed, fired),
+                _ => metrics.record_result(self.deadline, &result),
+            }
         }
+        result
+    }
 
-        let p = self.partition(arr, lo, hi);
-        self.recursive_sort(arr, lo, p - 1);
-        self.recursive_sort(arr, p + 1, hi);
+    /// Returns the time since the run started if the deadline has passed.
+    fn time_exceeded(&self) -> Option<Duration> {
+        let elapsed = self.clock.now().saturating_sub(self.start_time?);
+        (elapsed >= self.deadline).then_some(elapsed)
     }
 
-    fn partition(&self, arr: &mut [i32], lo: isize, hi: isize) -> isize {
-        let pivot = arr[hi as usize];
-        let mut i = lo - 1;
-        for j in lo..hi {
-            if self.time_exceeded() {
-                return i + 1;
+    /// Drains the checkpoint's work stack until it is empty or the deadline
+    /// fires, returning the elapsed time read by the check that fired.
+    fn run<T, F>(
+        &self,
+        arr: &mut [T],
+        checkpoint: &mut SortCheckpoint,
+        compare: &mut F,
+    ) -> Option<Duration>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        loop {
+            if let Some(fired) = self.time_exceeded() {
+                return Some(fired);
             }
-            if arr[j as usize] < pivot {
-                i += 1;
-                arr.swap(i as usize, j as usize);
+
+            let state = match checkpoint.active.take() {
+                Some(state) => state,
+                None => match checkpoint.pending.pop() {
+                    Some((lo, hi)) => PartitionState {
+                        lo,
+                        hi,
+                        i: lo,
+                        j: lo,
+                    },
+                    None => return None,
+                },
+            };
+
+            let (lo, hi) = (state.lo, state.hi);
+            let p = match self.partition(arr, state, compare) {
+                Ok(p) => p,
+                Err((interrupted, fired)) => {
+                    checkpoint.active = Some(interrupted);
+                    return Some(fired);
+                }
+            };
+            checkpoint.finalized_partitions += 1;
+
+            // Push the larger side first so the smaller one is handled next,
+            // keeping the stack O(log n) deep.
+            let mut sides = [(lo, p), (p + 1, hi)];
+            if sides[0].1 - sides[0].0 < sides[1].1 - sides[1].0 {
+                sides.swap(0, 1);
+            }
+            for (side_lo, side_hi) in sides {
+                if side_hi - side_lo > 1 {
+                    checkpoint.pending.push((side_lo, side_hi));
+                }
             }
         }
-        arr.swap((i + 1) as usize, hi as usize);
-        i + 1
     }
-}
-
-/// A tool for analyzing the Worst-Case Execution Time (WCET) of a given function.
-pub struct WcetAnalyzer {
-    /// A collection of execution time samples in milliseconds.
-    pub samples: Vec<f64>,
-}
 
-impl Default for WcetAnalyzer {
-    fn default() -> Self {
-        Self::new()
+    /// Continues a Lomuto partition pass. Returns the pivot's final index, or the
+    /// state to pick up from and the elapsed time if the deadline fired mid-pass.
+    fn partition<T, F>(
+        &self,
+        arr: &mut [T],
+        mut state: PartitionState,
+        compare: &mut F,
+    ) -> Result<usize, (PartitionState, Duration)>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let pivot = state.hi - 1;
+        while state.j < pivot {
+            if let Some(fired) = self.time_exceeded() {
+                return Err((state, fired));
+            }
+            if compare(&arr[state.j], &arr[pivot]) == Ordering::Less {
+                arr.swap(state.i, state.j);
+                state.i += 1;
+            }
+            state.j += 1;
+        }
+        arr.swap(state.i, pivot);
+        Ok(state.i)
     }
 }
 
-impl WcetAnalyzer {
-    /// Creates a new `WcetAnalyzer`.
-    pub fn new() -> Self {
-        WcetAnalyzer {
-            samples: Vec::new(),
-        }
+/// Scores how sorted `arr` is under `compare`, from `0.0` to `1.0`.
+///
+/// Inversions are counted with a merge sort over element indices, so the slice
+/// itself is left untouched and `T` need not be `Clone`.
+pub fn sortedness<T, F>(arr: &[T], mut compare: F) -> f64
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let n = arr.len();
+    if n < 2 {
+        return 1.0;
     }
+    let max_inversions = (n as f64) * (n as f64 - 1.0) / 2.0;
+    1.0 - count_inversions(arr, &mut compare) as f64 / max_inversions
+}
 
-    /// Measures the execution time of a function over a specified number of iterations.
-    pub fn measure<F>(&mut self, mut f: F, iterations: u32)
-    where
-        F: FnMut(),
-    {
-        self.samples.clear();
-        for _ in 0..iterations {
-            let start = Instant::now();
-            f();
-            self.samples.push(start.elapsed().as_secs_f64() * 1000.0); // in ms
+fn count_inversions<T, F>(arr: &[T], compare: &mut F) -> u64
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let mut idx: Vec<usize> = (0..arr.len()).collect();
+    let mut buf = idx.clone();
+    let mut inversions = 0u64;
+    let mut width = 1;
+    while width < idx.len() {
+        for lo in (0..idx.len()).step_by(2 * width) {
+            let mid = (lo + width).min(idx.len());
+            let hi = (lo + 2 * width).min(idx.len());
+            let (mut l, mut r, mut k) = (lo, mid, lo);
+            while l < mid && r < hi {
+                if compare(&arr[idx[r]], &arr[idx[l]]) == Ordering::Less {
+                    inversions += (mid - l) as u64;
+                    buf[k] = idx[r];
+                    r += 1;
+                } else {
+                    buf[k] = idx[l];
+                    l += 1;
+                }
+                k += 1;
+            }
+            buf[k..k + mid - l].copy_from_slice(&idx[l..mid]);
+            k += mid - l;
+            buf[k..k + hi - r].copy_from_slice(&idx[r..hi]);
         }
+        std::mem::swap(&mut idx, &mut buf);
+        width *= 2;
     }
+    inversions
 }
 
 #[cfg(test)]
 mod tests {
+    use super::clock::VirtualClock;
     use super::*;
     use rand::Rng;
 
@@ -107,9 +367,11 @@ mod tests {
         let mut arr: Vec<i32> = (0..1000).map(|_| rng.gen_range(0..10000)).collect();
 
         let mut sorter = AnytimeQuicksort::new(0); // 1 nanosecond deadline
-        sorter.sort(&mut arr);
+        let result = sorter.sort(&mut arr);
         let is_sorted = arr.windows(2).all(|w| w[0] <= w[1]);
         assert!(!is_sorted, "The array should not be fully sorted with a 1ns deadline");
+        assert!(!result.completed, "The result should report the sort as incomplete");
+        assert!(result.sortedness < 1.0, "An unsorted array should not score as fully sorted");
 
         let mut analyzer = WcetAnalyzer::new();
         analyzer.measure(|| {
@@ -118,4 +380,67 @@ mod tests {
         }, 100);
         assert_eq!(analyzer.samples.len(), 100, "WCET analysis should have 100 samples");
     }
+
+    #[test]
+    fn test_verify_anytime_result_quality() {
+        let mut words = vec!["pear", "fig", "apple", "kiwi", "banana"];
+        let mut sorter = AnytimeQuicksort::new(1_000);
+        let result = sorter.sort_by(&mut words, |a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        assert!(result.completed, "A tiny array should sort well within the deadline");
+        assert_eq!(result.sortedness, 1.0);
+        assert_eq!(words, vec!["banana", "apple", "kiwi", "pear", "fig"]);
+
+        let reversed: Vec<i32> = (0..10).rev().collect();
+        assert_eq!(sortedness(&reversed, i32::cmp), 0.0, "A reversed array has every pair inverted");
+        assert_eq!(sortedness(&[1, 3, 2, 4], i32::cmp), 1.0 - 1.0 / 6.0);
+    }
+
+    #[test]
+    fn test_verify_resumable_sort() {
+        let mut rng = rand::thread_rng();
+        let mut arr: Vec<u32> = (0..20_000).map(|_| rng.gen()).collect();
+        let mut checkpoint = SortCheckpoint::new(arr.len());
+        // One microsecond per deadline check: each 1ms frame does ~1000 units of work.
+        let clock = VirtualClock::stepping(Duration::from_micros(1));
+        let mut sorter = AnytimeQuicksort::with_clock(Duration::from_millis(1), &clock);
+
+        let mut frames = 0;
+        let mut last_sortedness = 0.0;
+        while !checkpoint.is_complete() {
+            let result = sorter.resume(&mut arr, &mut checkpoint);
+            assert!(result.sortedness >= last_sortedness, "Quality should never regress between frames");
+            last_sortedness = result.sortedness;
+            frames += 1;
+        }
+
+        assert!(frames > 1, "A large sort should need more than one 1ms frame");
+        assert_eq!(checkpoint.remaining(), 0);
+        assert!(arr.windows(2).all(|w| w[0] <= w[1]), "The resumed sort should finish correctly");
+    }
+
+    #[test]
+    fn test_verify_deterministic_cutoff() {
+        let input: Vec<i32> = (0..500).map(|i| (i * 7919) % 1000).collect();
+        let run = || {
+            let clock = VirtualClock::stepping(Duration::from_micros(1));
+            let mut sorter = AnytimeQuicksort::with_clock(Duration::from_micros(300), &clock);
+            let mut arr = input.clone();
+            let result = sorter.sort(&mut arr);
+            (arr, result, clock.reads())
+        };
+
+        let (first_arr, first, reads) = run();
+        let (second_arr, second, _) = run();
+        assert!(!first.completed);
+        assert_eq!(first_arr, second_arr, "A virtual clock should cut off at the same point every run");
+        assert_eq!(first, second);
+        // 300 checks under budget, one that fires, then the final elapsed reading.
+        assert_eq!(reads, 302);
+        assert_eq!(first.elapsed, Duration::from_micros(301));
+
+        let clock = VirtualClock::new();
+        let mut analyzer = WcetAnalyzer::with_clock(&clock);
+        analyzer.measure(|| clock.advance(Duration::from_millis(3)), 5);
+        assert_eq!(analyzer.samples, vec![3.0; 5]);
+    }
 }
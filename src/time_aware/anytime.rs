@@ -0,0 +1,490 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+/// A computation that can be interrupted after any step and still produce a
+/// usable answer whose quality improves as more steps are taken.
+pub trait Anytime {
+    /// The type of answer the computation produces.
+    type Output;
+
+    /// Performs one small, bounded unit of work. Calling `step` on a complete
+    /// computation has no effect.
+    fn step(&mut self);
+
+    /// Returns the best answer found so far.
+    fn current_best(&self) -> Self::Output;
+
+    /// Returns the quality of `current_best`, from `0.0` to `1.0`.
+    fn quality(&self) -> f64;
+
+    /// Returns `true` once further steps cannot improve the answer.
+    fn is_complete(&self) -> bool;
+}
+
+/// The outcome of driving an [`Anytime`] computation against a deadline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunReport<O> {
+    /// The best answer available when the run stopped.
+    pub output: O,
+    /// The quality of `output`, from `0.0` to `1.0`.
+    pub quality: f64,
+    /// Whether the computation finished before the deadline.
+    pub completed: bool,
+    /// The number of steps taken during this run.
+    pub steps: u64,
+    /// The wall-clock time spent stepping.
+    pub elapsed: Duration,
+}
+
+/// Steps `algorithm` until it completes or `deadline` passes, then reports its
+/// best answer. The deadline is checked between steps, so a run overshoots it
+/// by at most one step.
+pub fn run_until<A: Anytime>(algorithm: &mut A, deadline: Instant) -> RunReport<A::Output> {
+    let start = Instant::now();
+    let mut steps = 0;
+    while !algorithm.is_complete() && Instant::now() < deadline {
+        algorithm.step();
+        steps += 1;
+    }
+    RunReport {
+        output: algorithm.current_best(),
+        quality: algorithm.quality(),
+        completed: algorithm.is_complete(),
+        steps,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Anytime selection of the `k` largest items.
+///
+/// Each step scans one chunk of the input into a bounded min-heap. Quality is
+/// the fraction of the input scanned, which is the expected recall of the
+/// current answer when the input is in random order.
+pub struct AnytimeTopK<T> {
+    items: Vec<T>,
+    k: usize,
+    chunk_size: usize,
+    scanned: usize,
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord + Clone> AnytimeTopK<T> {
+    /// Creates a new `AnytimeTopK` that scans `chunk_size` items per step.
+    pub fn new(items: Vec<T>, k: usize, chunk_size: usize) -> Self {
+        AnytimeTopK {
+            items,
+            k,
+            chunk_size: chunk_size.max(1),
+            scanned: 0,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+}
+
+impl<T: Ord + Clone> Anytime for AnytimeTopK<T> {
+    type Output = Vec<T>;
+
+    fn step(&mut self) {
+        let end = (self.scanned + self.chunk_size).min(self.items.len());
+        for item in &self.items[self.scanned..end] {
+            if self.heap.len() < self.k {
+                self.heap.push(Reverse(item.clone()));
+            } else if self.heap.peek().is_some_and(|Reverse(min)| item > min) {
+                self.heap.pop();
+                self.heap.push(Reverse(item.clone()));
+            }
+        }
+        self.scanned = end;
+    }
+
+    /// Returns the largest items seen so far, in descending order.
+    fn current_best(&self) -> Vec<T> {
+        let mut best: Vec<T> = self.heap.iter().map(|Reverse(item)| item.clone()).collect();
+        best.sort_by(|a, b| b.cmp(a));
+        best
+    }
+
+    fn quality(&self) -> f64 {
+        if self.items.is_empty() {
+            1.0
+        } else {
+            self.scanned as f64 / self.items.len() as f64
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.scanned == self.items.len()
+    }
+}
+
+/// Anytime estimation of the `p`-th quantile of a data set by sampling without
+/// replacement.
+///
+/// Quality is `1 - 2 * z * se`, clamped to `[0, 1]`, where `se` is the
+/// finite-population standard error of the sample quantile's rank and `z` is
+/// the 95% normal quantile. It reaches `1.0` once every value has been sampled.
+pub struct AnytimePercentile {
+    data: Vec<f64>,
+    p: f64,
+    batch_size: usize,
+    sampled: usize,
+    rng: SmallRng,
+}
+
+impl AnytimePercentile {
+    /// Creates a new `AnytimePercentile` for the quantile `p` (in `[0, 1]`),
+    /// drawing `batch_size` samples per step.
+    pub fn new(data: Vec<f64>, p: f64, batch_size: usize) -> Self {
+        AnytimePercentile {
+            data,
+            p: p.clamp(0.0, 1.0),
+            batch_size: batch_size.max(1),
+            sampled: 0,
+            rng: SmallRng::from_entropy(),
+        }
+    }
+
+    /// Seeds the sampler, making the sequence of estimates reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl Anytime for AnytimePercentile {
+    type Output = Option<f64>;
+
+    fn step(&mut self) {
+        let n = self.data.len();
+        let end = (self.sampled + self.batch_size).min(n);
+        // A partial Fisher-Yates shuffle: `data[..sampled]` is a uniform sample.
+        for i in self.sampled..end {
+            let j = self.rng.gen_range(i..n);
+            self.data.swap(i, j);
+        }
+        self.sampled = end;
+    }
+
+    /// Returns the quantile of the sample drawn so far, or `None` before the
+    /// first step.
+    fn current_best(&self) -> Option<f64> {
+        if self.sampled == 0 {
+            return None;
+        }
+        let mut sample = self.data[..self.sampled].to_vec();
+        let rank = ((sample.len() - 1) as f64 * self.p).round() as usize;
+        let (_, value, _) = sample.select_nth_unstable_by(rank, f64::total_cmp);
+        Some(*value)
+    }
+
+    fn quality(&self) -> f64 {
+        let (n, m) = (self.data.len() as f64, self.sampled as f64);
+        if self.sampled == self.data.len() {
+            return 1.0;
+        }
+        if self.sampled == 0 {
+            return 0.0;
+        }
+        let correction = if n > 1.0 { (n - m) / (n - 1.0) } else { 0.0 };
+        let se = (self.p * (1.0 - self.p) / m * correction).sqrt();
+        (1.0 - 2.0 * 1.96 * se).clamp(0.0, 1.0)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.sampled == self.data.len()
+    }
+}
+
+/// An item that can be packed into a knapsack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KnapsackItem {
+    /// The capacity the item uses.
+    pub weight: f64,
+    /// The value gained by packing the item.
+    pub value: f64,
+}
+
+/// A set of packed items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnapsackSolution {
+    /// Indices of the packed items, in ascending order.
+    pub selected: Vec<usize>,
+    /// The total value of the packed items.
+    pub value: f64,
+    /// The total weight of the packed items.
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KnapsackPhase {
+    /// Considering items in order of decreasing value density.
+    Greedy {
+        next: usize,
+    },
+    /// Looking for value-increasing single-item swaps.
+    Improve {
+        next: usize,
+        improved: bool,
+    },
+    Done,
+}
+
+/// Anytime 0/1 knapsack: a greedy fill by value density followed by local
+/// search over single-item swaps.
+///
+/// Quality is the ratio of the current value to the Dantzig bound (the optimum
+/// of the fractional relaxation), so it is a guaranteed lower bound on how
+/// close the answer is to optimal. Items without positive value can only lower
+/// the total and are never packed.
+pub struct AnytimeKnapsack {
+    items: Vec<KnapsackItem>,
+    capacity: f64,
+    by_density: Vec<usize>,
+    selected: Vec<bool>,
+    value: f64,
+    weight: f64,
+    upper_bound: f64,
+    phase: KnapsackPhase,
+}
+
+impl AnytimeKnapsack {
+    /// Creates a new `AnytimeKnapsack`. Ranking the items by density costs
+    /// `O(n log n)` up front.
+    pub fn new(items: Vec<KnapsackItem>, capacity: f64) -> Self {
+        let mut by_density: Vec<usize> =
+            (0..items.len()).filter(|&i| items[i].value > 0.0).collect();
+        by_density.sort_by(|&a, &b| density(&items[b]).total_cmp(&density(&items[a])));
+        let upper_bound = dantzig_bound(&items, &by_density, capacity);
+        AnytimeKnapsack {
+            selected: vec![false; items.len()],
+            items,
+            capacity,
+            by_density,
+            value: 0.0,
+            weight: 0.0,
+            upper_bound,
+            phase: KnapsackPhase::Greedy { next: 0 },
+        }
+    }
+
+    fn toggle(&mut self, idx: usize) {
+        let item = self.items[idx];
+        if self.selected[idx] {
+            self.value -= item.value;
+            self.weight -= item.weight;
+        } else {
+            self.value += item.value;
+            self.weight += item.weight;
+        }
+        self.selected[idx] = !self.selected[idx];
+    }
+
+    /// Replaces the greedy packing with the single most valuable item if that
+    /// is better, which bounds the greedy answer at half the optimum.
+    fn apply_best_single_item(&mut self) {
+        let best_single = (0..self.items.len())
+            .filter(|&i| self.items[i].weight <= self.capacity)
+            .max_by(|&a, &b| self.items[a].value.total_cmp(&self.items[b].value));
+        if let Some(best) = best_single {
+            if self.items[best].value > self.value {
+                for i in 0..self.items.len() {
+                    if self.selected[i] {
+                        self.toggle(i);
+                    }
+                }
+                self.toggle(best);
+            }
+        }
+    }
+
+    /// Swaps the selected item `out` for the unselected item that most improves
+    /// the total value while still fitting, if any.
+    fn improve_by_swapping(&mut self, out: usize) -> bool {
+        let slack = self.capacity - self.weight + self.items[out].weight;
+        let best_in = (0..self.items.len())
+            .filter(|&j| !self.selected[j] && self.items[j].weight <= slack)
+            .filter(|&j| self.items[j].value > self.items[out].value)
+            .max_by(|&a, &b| self.items[a].value.total_cmp(&self.items[b].value));
+        match best_in {
+            Some(j) => {
+                self.toggle(out);
+                self.toggle(j);
+                // Filling the slack left behind may now admit more items.
+                for pos in 0..self.by_density.len() {
+                    let k = self.by_density[pos];
+                    if !self.selected[k] && self.weight + self.items[k].weight <= self.capacity {
+                        self.toggle(k);
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Anytime for AnytimeKnapsack {
+    type Output = KnapsackSolution;
+
+    fn step(&mut self) {
+        self.phase = match self.phase {
+            KnapsackPhase::Greedy { next } if next < self.by_density.len() => {
+                let idx = self.by_density[next];
+                if self.weight + self.items[idx].weight <= self.capacity {
+                    self.toggle(idx);
+                }
+                KnapsackPhase::Greedy { next: next + 1 }
+            }
+            KnapsackPhase::Greedy { .. } => {
+                self.apply_best_single_item();
+                KnapsackPhase::Improve {
+                    next: 0,
+                    improved: false,
+                }
+            }
+            KnapsackPhase::Improve { next, improved } if next < self.items.len() => {
+                let improved = (self.selected[next] && self.improve_by_swapping(next)) || improved;
+                KnapsackPhase::Improve {
+                    next: next + 1,
+                    improved,
+                }
+            }
+            KnapsackPhase::Improve { improved: true, .. } => KnapsackPhase::Improve {
+                next: 0,
+                improved: false,
+            },
+            KnapsackPhase::Improve {
+                improved: false, ..
+            }
+            | KnapsackPhase::Done => KnapsackPhase::Done,
+        };
+    }
+
+    fn current_best(&self) -> KnapsackSolution {
+        KnapsackSolution {
+            selected: (0..self.items.len())
+                .filter(|&i| self.selected[i])
+                .collect(),
+            value: self.value,
+            weight: self.weight,
+        }
+    }
+
+    fn quality(&self) -> f64 {
+        if self.upper_bound <= 0.0 {
+            1.0
+        } else {
+            (self.value / self.upper_bound).clamp(0.0, 1.0)
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.phase == KnapsackPhase::Done
+    }
+}
+
+fn density(item: &KnapsackItem) -> f64 {
+    if item.weight <= 0.0 {
+        f64::INFINITY
+    } else {
+        item.value / item.weight
+    }
+}
+
+/// The optimum of the fractional knapsack, an upper bound on the 0/1 optimum.
+fn dantzig_bound(items: &[KnapsackItem], by_density: &[usize], capacity: f64) -> f64 {
+    let mut remaining = capacity;
+    let mut bound = 0.0;
+    for &idx in by_density {
+        let item = items[idx];
+        if item.weight <= remaining {
+            remaining -= item.weight;
+            bound += item.value;
+        } else {
+            if item.weight > 0.0 {
+                bound += item.value * remaining / item.weight;
+            }
+            break;
+        }
+    }
+    bound
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_anytime_framework() {
+        let mut top_k = AnytimeTopK::new((0..10_000).collect::<Vec<u32>>(), 3, 100);
+        let report = run_until(&mut top_k, Instant::now() + Duration::from_secs(5));
+        assert!(
+            report.completed,
+            "Top-k over 10k items should finish well within 5s"
+        );
+        assert_eq!(report.steps, 100);
+        assert_eq!(report.output, vec![9_999, 9_998, 9_997]);
+        assert_eq!(report.quality, 1.0);
+
+        let data: Vec<f64> = (0..1_001).map(f64::from).collect();
+        let mut percentile = AnytimePercentile::new(data, 0.5, 50).with_seed(7);
+        percentile.step();
+        let early_quality = percentile.quality();
+        let estimate = percentile.current_best().unwrap();
+        assert!(
+            (estimate - 500.0).abs() < 200.0,
+            "A 50-sample median should be roughly central"
+        );
+        let report = run_until(&mut percentile, Instant::now() + Duration::from_secs(5));
+        assert!(report.quality > early_quality);
+        assert_eq!(
+            report.output,
+            Some(500.0),
+            "The full sample gives the exact median"
+        );
+
+        // Greedy by density takes the two light items (value 10), but the heavy
+        // item alone is worth more; the best-single-item check must catch it.
+        let items = vec![
+            KnapsackItem {
+                weight: 1.0,
+                value: 6.0,
+            },
+            KnapsackItem {
+                weight: 1.0,
+                value: 4.0,
+            },
+            KnapsackItem {
+                weight: 10.0,
+                value: 30.0,
+            },
+        ];
+        let mut knapsack = AnytimeKnapsack::new(items, 10.0);
+        let report = run_until(&mut knapsack, Instant::now() + Duration::from_secs(5));
+        assert!(report.completed);
+        assert_eq!(report.output.selected, vec![2]);
+        assert_eq!(report.output.value, 30.0);
+        assert!(report.quality > 0.8 && report.quality <= 1.0);
+
+        // A weightless item of negative value ranks first by density, but
+        // packing it only lowers the total.
+        let items = vec![
+            KnapsackItem {
+                weight: 0.0,
+                value: -5.0,
+            },
+            KnapsackItem {
+                weight: 1.0,
+                value: 10.0,
+            },
+        ];
+        let mut knapsack = AnytimeKnapsack::new(items, 1.0);
+        let report = run_until(&mut knapsack, Instant::now() + Duration::from_secs(5));
+        assert_eq!(report.output.selected, vec![1]);
+        assert_eq!(report.output.value, 10.0);
+        assert_eq!(report.quality, 1.0);
+    }
+}
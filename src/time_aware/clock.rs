@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A source of monotonic time for deadline checks and measurements.
+///
+/// Time is reported as the duration since the clock's own epoch, which lets
+/// virtual clocks start at zero and be driven by hand.
+pub trait Clock {
+    /// Returns the time elapsed since the clock's epoch.
+    fn now(&self) -> Duration;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+/// The real monotonic wall clock, measured from the moment it was created.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    epoch: Instant,
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MonotonicClock {
+    /// Creates a new `MonotonicClock` whose epoch is now.
+    pub fn new() -> Self {
+        MonotonicClock {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+}
+
+/// A deterministic clock that only moves when told to.
+///
+/// Time can be advanced by hand with [`VirtualClock::advance`], and a clock
+/// created with [`VirtualClock::stepping`] also advances by a fixed tick every
+/// time it is read, so "one tick per deadline check" reproduces the exact same
+/// cutoff on every run. The clock is thread-safe and can be shared by
+/// reference or through an `Arc` while a test drives it.
+#[derive(Debug, Default)]
+pub struct VirtualClock {
+    now_nanos: AtomicU64,
+    tick_nanos: u64,
+    reads: AtomicU64,
+}
+
+impl VirtualClock {
+    /// Creates a clock at time zero that only moves when advanced by hand.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a clock at time zero that advances by `tick` after every read.
+    pub fn stepping(tick: Duration) -> Self {
+        VirtualClock {
+            tick_nanos: tick.as_nanos() as u64,
+            ..Self::default()
+        }
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        self.now_nanos
+            .fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    /// Returns how many times the clock has been read.
+    pub fn reads(&self) -> u64 {
+        self.reads.load(Ordering::SeqCst)
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        Duration::from_nanos(self.now_nanos.fetch_add(self.tick_nanos, Ordering::SeqCst))
+    }
+}
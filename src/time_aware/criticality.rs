@@ -0,0 +1,493 @@
+use super::clock::Clock;
+use super::scheduling::Schedulability;
+use super::WcetAnalyzer;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+
+/// The criticality level of a task, or the mode of a mixed-criticality system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Criticality {
+    /// Best-effort work that may be dropped to protect `Hi` tasks.
+    Lo,
+    /// Safety-critical work that must meet its deadlines in every mode.
+    Hi,
+}
+
+/// A periodic task with one WCET budget per criticality level.
+///
+/// The `Lo` budget is the optimistic estimate the system is provisioned for
+/// in normal operation; the `Hi` budget is the pessimistic one certification
+/// demands. `Lo` tasks only have the former.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedCriticalityTask {
+    /// The name of the task.
+    pub name: String,
+    /// The criticality level of the task.
+    pub criticality: Criticality,
+    /// The execution time budget in `Lo` mode.
+    pub wcet_lo: Duration,
+    /// The execution time budget in `Hi` mode; equals `wcet_lo` for `Lo` tasks.
+    pub wcet_hi: Duration,
+    /// The period of the task.
+    pub period: Duration,
+    /// The relative deadline of each job.
+    pub deadline: Duration,
+    /// In simulations, the probability that a job of a `Hi` task runs for its
+    /// full `Hi` budget instead of its `Lo` budget.
+    pub overrun_probability: f64,
+}
+
+impl MixedCriticalityTask {
+    /// Creates a best-effort task whose deadline equals its period.
+    pub fn lo(name: &str, wcet: Duration, period: Duration) -> Self {
+        MixedCriticalityTask {
+            name: name.to_string(),
+            criticality: Criticality::Lo,
+            wcet_lo: wcet,
+            wcet_hi: wcet,
+            period,
+            deadline: period,
+            overrun_probability: 0.0,
+        }
+    }
+
+    /// Creates a safety-critical task whose deadline equals its period.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `wcet_hi` is smaller than `wcet_lo`.
+    pub fn hi(name: &str, wcet_lo: Duration, wcet_hi: Duration, period: Duration) -> Self {
+        assert!(wcet_hi >= wcet_lo, "the Hi budget must cover the Lo budget");
+        MixedCriticalityTask {
+            criticality: Criticality::Hi,
+            wcet_hi,
+            ..Self::lo(name, wcet_lo, period)
+        }
+    }
+
+    /// Creates a safety-critical task whose budgets are the pWCETs estimated
+    /// by `analyzer` at two exceedance probabilities, for example `1e-3` for
+    /// `Lo` mode and `1e-9` for `Hi` mode. Returns `None` if the analyzer
+    /// cannot fit an estimate.
+    pub fn from_wcet_analyzer<C: Clock>(
+        name: &str,
+        analyzer: &WcetAnalyzer<C>,
+        lo_exceedance: f64,
+        hi_exceedance: f64,
+        period: Duration,
+    ) -> Option<Self> {
+        let to_duration = |ms: f64| Duration::from_secs_f64(ms.max(0.0) / 1000.0);
+        let wcet_lo = to_duration(analyzer.pwcet(lo_exceedance)?);
+        let wcet_hi = to_duration(analyzer.pwcet(hi_exceedance)?);
+        Some(Self::hi(name, wcet_lo, wcet_hi.max(wcet_lo), period))
+    }
+
+    /// Sets a relative deadline different from the period.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Sets the probability that a simulated job overruns its `Lo` budget.
+    pub fn with_overrun_probability(mut self, probability: f64) -> Self {
+        self.overrun_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns the budget that applies at `level`.
+    pub fn wcet(&self, level: Criticality) -> Duration {
+        match level {
+            Criticality::Lo => self.wcet_lo,
+            Criticality::Hi => self.wcet_hi,
+        }
+    }
+
+    /// Returns the task's utilization under the budget of `level`.
+    pub fn utilization(&self, level: Criticality) -> f64 {
+        self.wcet(level).as_secs_f64() / self.period.as_secs_f64()
+    }
+}
+
+/// The result of the EDF-VD schedulability test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdfVdTest {
+    /// Utilization of `Lo` tasks under their `Lo` budgets.
+    pub lo_utilization: f64,
+    /// Utilization of `Hi` tasks under their `Lo` budgets.
+    pub hi_utilization_lo: f64,
+    /// Utilization of `Hi` tasks under their `Hi` budgets.
+    pub hi_utilization_hi: f64,
+    /// The factor `x` that shrinks `Hi` task deadlines in `Lo` mode. `1.0`
+    /// means plain EDF already suffices.
+    pub scaling_factor: f64,
+    /// The verdict.
+    pub verdict: Schedulability,
+}
+
+/// A switch of the system from `Lo` to `Hi` mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModeSwitch {
+    /// When the overrun was detected.
+    pub at: Duration,
+    /// The `Hi` task whose job exhausted its `Lo` budget.
+    pub task: String,
+    /// The number of pending `Lo` jobs dropped at the switch.
+    pub dropped: usize,
+    /// When the processor next idled and the system returned to `Lo` mode,
+    /// or `None` if it was still in `Hi` mode at the end of the horizon.
+    pub returned_at: Option<Duration>,
+}
+
+/// Per-task results of a mixed-criticality simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedCriticalityTaskReport {
+    /// The name of the task.
+    pub name: String,
+    /// The criticality level of the task.
+    pub criticality: Criticality,
+    /// The number of jobs released within the horizon.
+    pub jobs_released: usize,
+    /// The number of jobs that ran to completion within the horizon.
+    pub jobs_completed: usize,
+    /// Jobs abandoned because the system was in `Hi` mode.
+    pub jobs_dropped: usize,
+    /// Jobs that finished late, plus unfinished, undropped jobs whose deadline
+    /// passed.
+    pub deadline_misses: usize,
+    /// The longest observed release-to-completion time.
+    pub max_response_time: Duration,
+}
+
+/// The results of simulating a mixed-criticality task set under EDF-VD.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedCriticalityReport {
+    /// The simulated time span.
+    pub horizon: Duration,
+    /// The time the processor spent executing jobs.
+    pub busy_time: Duration,
+    /// The time spent in `Hi` mode.
+    pub hi_mode_time: Duration,
+    /// Every switch to `Hi` mode, in order.
+    pub mode_switches: Vec<ModeSwitch>,
+    /// Results for each task, in task set order.
+    pub tasks: Vec<MixedCriticalityTaskReport>,
+}
+
+impl MixedCriticalityReport {
+    /// Returns the deadline misses of tasks at or above `level`.
+    pub fn deadline_misses(&self, level: Criticality) -> usize {
+        self.tasks
+            .iter()
+            .filter(|t| t.criticality >= level)
+            .map(|t| t.deadline_misses)
+            .sum()
+    }
+
+    /// Returns the total number of dropped `Lo` jobs.
+    pub fn jobs_dropped(&self) -> usize {
+        self.tasks.iter().map(|t| t.jobs_dropped).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Job {
+    task: usize,
+    release: u64,
+    deadline: u64,
+    virtual_deadline: u64,
+    executed: u64,
+    demand: u64,
+}
+
+/// A discrete-event simulator for the EDF-VD mixed-criticality scheduler
+/// (Baruah et al., 2012) on one processor.
+///
+/// In `Lo` mode jobs are scheduled EDF, but `Hi` tasks are given virtual
+/// deadlines shortened by the scaling factor `x`, which reserves room for
+/// their `Hi` budgets. When a `Hi` job runs for its whole `Lo` budget without
+/// finishing, the system switches to `Hi` mode: pending `Lo` jobs are dropped,
+/// new `Lo` releases are dropped on arrival, and `Hi` jobs revert to their
+/// real deadlines. The system returns to `Lo` mode the next time the
+/// processor idles.
+pub struct EdfVdSimulator {
+    tasks: Vec<MixedCriticalityTask>,
+    rng: SmallRng,
+}
+
+impl EdfVdSimulator {
+    /// Creates a new `EdfVdSimulator` for the given task set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any task has a zero period.
+    pub fn new(tasks: Vec<MixedCriticalityTask>) -> Self {
+        assert!(
+            tasks.iter().all(|t| !t.period.is_zero()),
+            "every task needs a positive period"
+        );
+        EdfVdSimulator {
+            tasks,
+            rng: SmallRng::from_entropy(),
+        }
+    }
+
+    /// Seeds the overrun generator, making simulations reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Returns the tasks being simulated.
+    pub fn tasks(&self) -> &[MixedCriticalityTask] {
+        &self.tasks
+    }
+
+    /// Applies the EDF-VD utilization test and computes the scaling factor.
+    ///
+    /// The test is sufficient for implicit deadlines: if either mode alone
+    /// overloads the processor the set is `Unschedulable`, and if neither
+    /// plain EDF nor EDF-VD can guarantee it the verdict is `Inconclusive`.
+    pub fn schedulability(&self) -> EdfVdTest {
+        let sum = |criticality: Criticality, level: Criticality| -> f64 {
+            self.tasks
+                .iter()
+                .filter(|t| t.criticality == criticality)
+                .map(|t| t.utilization(level))
+                .sum()
+        };
+        let lo_utilization = sum(Criticality::Lo, Criticality::Lo);
+        let hi_utilization_lo = sum(Criticality::Hi, Criticality::Lo);
+        let hi_utilization_hi = sum(Criticality::Hi, Criticality::Hi);
+
+        let (scaling_factor, verdict) = if lo_utilization + hi_utilization_hi <= 1.0 {
+            (1.0, Schedulability::Schedulable)
+        } else if lo_utilization + hi_utilization_lo > 1.0 || hi_utilization_hi > 1.0 {
+            (1.0, Schedulability::Unschedulable)
+        } else {
+            let x = hi_utilization_lo / (1.0 - lo_utilization);
+            if x * lo_utilization + hi_utilization_hi <= 1.0 {
+                (x, Schedulability::Schedulable)
+            } else {
+                (1.0, Schedulability::Inconclusive)
+            }
+        };
+
+        EdfVdTest {
+            lo_utilization,
+            hi_utilization_lo,
+            hi_utilization_hi,
+            scaling_factor,
+            verdict,
+        }
+    }
+
+    /// Simulates the task set under EDF-VD from time zero to `horizon`, using
+    /// the scaling factor computed by `schedulability`.
+    pub fn simulate(&mut self, horizon: Duration) -> MixedCriticalityReport {
+        let scaling_factor = self.schedulability().scaling_factor;
+        let horizon_ns = horizon.as_nanos() as u64;
+        let ns = |d: Duration| d.as_nanos() as u64;
+        let mut next_release = vec![0u64; self.tasks.len()];
+        let mut reports: Vec<MixedCriticalityTaskReport> = self
+            .tasks
+            .iter()
+            .map(|t| MixedCriticalityTaskReport {
+                name: t.name.clone(),
+                criticality: t.criticality,
+                jobs_released: 0,
+                jobs_completed: 0,
+                jobs_dropped: 0,
+                deadline_misses: 0,
+                max_response_time: Duration::ZERO,
+            })
+            .collect();
+        let mut mode_switches: Vec<ModeSwitch> = Vec::new();
+        let mut jobs: Vec<Job> = Vec::new();
+        let mut mode = Criticality::Lo;
+        let mut busy = 0u64;
+        let mut hi_mode = 0u64;
+        let mut now = 0u64;
+
+        while now < horizon_ns {
+            for (i, task) in self.tasks.iter().enumerate() {
+                while next_release[i] <= now {
+                    let release = next_release[i];
+                    next_release[i] += ns(task.period);
+                    reports[i].jobs_released += 1;
+                    if mode == Criticality::Hi && task.criticality == Criticality::Lo {
+                        reports[i].jobs_dropped += 1;
+                        continue;
+                    }
+                    let overruns = task.criticality == Criticality::Hi
+                        && self.rng.gen_bool(task.overrun_probability);
+                    let deadline = ns(task.deadline);
+                    let virtual_deadline = match task.criticality {
+                        Criticality::Lo => deadline,
+                        Criticality::Hi => (deadline as f64 * scaling_factor) as u64,
+                    };
+                    jobs.push(Job {
+                        task: i,
+                        release,
+                        deadline: release + deadline,
+                        virtual_deadline: release + virtual_deadline,
+                        executed: 0,
+                        demand: ns(if overruns { task.wcet_hi } else { task.wcet_lo }),
+                    });
+                }
+            }
+
+            let next_event = next_release.iter().copied().min().unwrap_or(u64::MAX);
+            let next_event = next_event.min(horizon_ns);
+            let running = jobs
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, job)| {
+                    let deadline = match mode {
+                        Criticality::Lo => job.virtual_deadline,
+                        Criticality::Hi => job.deadline,
+                    };
+                    (deadline, job.release, job.task)
+                })
+                .map(|(idx, _)| idx);
+            let Some(idx) = running else {
+                // An idle instant: any overload has been worked off.
+                if mode == Criticality::Hi {
+                    mode = Criticality::Lo;
+                    if let Some(switch) = mode_switches.last_mut() {
+                        switch.returned_at = Some(Duration::from_nanos(now));
+                    }
+                }
+                now = next_event;
+                continue;
+            };
+
+            let task = &self.tasks[jobs[idx].task];
+            let mut slice = (jobs[idx].demand - jobs[idx].executed).min(next_event - now);
+            let monitored = mode == Criticality::Lo && task.criticality == Criticality::Hi;
+            if monitored {
+                // Stop exactly when the Lo budget runs out to detect overruns.
+                slice = slice.min(ns(task.wcet_lo) - jobs[idx].executed);
+            }
+            now += slice;
+            busy += slice;
+            if mode == Criticality::Hi {
+                hi_mode += slice;
+            }
+            jobs[idx].executed += slice;
+
+            let job = jobs[idx];
+            if job.executed == job.demand {
+                jobs.swap_remove(idx);
+                let report = &mut reports[job.task];
+                let response = now - job.release;
+                report.jobs_completed += 1;
+                report.max_response_time =
+                    report.max_response_time.max(Duration::from_nanos(response));
+                if now > job.deadline {
+                    report.deadline_misses += 1;
+                }
+            } else if monitored && job.executed == ns(task.wcet_lo) {
+                mode = Criticality::Hi;
+                let before = jobs.len();
+                for job in jobs.iter() {
+                    if self.tasks[job.task].criticality == Criticality::Lo {
+                        reports[job.task].jobs_dropped += 1;
+                    }
+                }
+                jobs.retain(|job| self.tasks[job.task].criticality == Criticality::Hi);
+                mode_switches.push(ModeSwitch {
+                    at: Duration::from_nanos(now),
+                    task: task.name.clone(),
+                    dropped: before - jobs.len(),
+                    returned_at: None,
+                });
+            }
+        }
+
+        for job in &jobs {
+            if job.deadline <= horizon_ns {
+                reports[job.task].deadline_misses += 1;
+            }
+        }
+
+        MixedCriticalityReport {
+            horizon,
+            busy_time: Duration::from_nanos(busy),
+            hi_mode_time: Duration::from_nanos(hi_mode),
+            mode_switches,
+            tasks: reports,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_edf_vd_mode_switches() {
+        let ms = Duration::from_millis;
+        // Under Hi budgets alone the set needs 110% of the processor, but
+        // EDF-VD with x = 0.5 keeps the Hi tasks safe by shedding the logger.
+        let tasks = |overrun: f64| {
+            vec![
+                MixedCriticalityTask::lo("logger", ms(2), ms(10)),
+                MixedCriticalityTask::hi("brake", ms(2), ms(5), ms(10))
+                    .with_overrun_probability(overrun),
+                MixedCriticalityTask::hi("steer", ms(1), ms(2), ms(5))
+                    .with_overrun_probability(overrun),
+            ]
+        };
+
+        let mut simulator = EdfVdSimulator::new(tasks(0.0)).with_seed(3);
+        let test = simulator.schedulability();
+        assert_eq!(test.verdict, Schedulability::Schedulable);
+        assert!((test.lo_utilization - 0.2).abs() < 1e-9);
+        assert!((test.hi_utilization_hi - 0.9).abs() < 1e-9);
+        assert!((test.scaling_factor - 0.5).abs() < 1e-9);
+
+        let calm = simulator.simulate(ms(1_000));
+        assert!(
+            calm.mode_switches.is_empty(),
+            "No job overruns its Lo budget"
+        );
+        assert_eq!(calm.jobs_dropped(), 0);
+        assert_eq!(calm.deadline_misses(Criticality::Lo), 0);
+        assert_eq!(calm.tasks[0].jobs_completed, 100);
+
+        let mut simulator = EdfVdSimulator::new(tasks(0.3)).with_seed(3);
+        let stressed = simulator.simulate(ms(1_000));
+        assert!(!stressed.mode_switches.is_empty());
+        assert_eq!(
+            stressed.deadline_misses(Criticality::Hi),
+            0,
+            "Hi tasks meet every deadline despite overruns"
+        );
+        assert!(
+            stressed.tasks[0].jobs_dropped > 0,
+            "The logger is shed in Hi mode"
+        );
+        assert_eq!(
+            stressed.tasks[1].jobs_dropped + stressed.tasks[2].jobs_dropped,
+            0
+        );
+        assert!(stressed.hi_mode_time > Duration::ZERO);
+        for switch in &stressed.mode_switches {
+            let returned = switch.returned_at.unwrap_or(stressed.horizon);
+            assert!(
+                returned >= switch.at,
+                "The system returns to Lo mode when idle"
+            );
+        }
+
+        let overloaded = EdfVdSimulator::new(vec![
+            MixedCriticalityTask::lo("video", ms(6), ms(10)),
+            MixedCriticalityTask::hi("brake", ms(3), ms(8), ms(10)),
+        ]);
+        assert_eq!(
+            overloaded.schedulability().verdict,
+            Schedulability::Inconclusive
+        );
+    }
+}
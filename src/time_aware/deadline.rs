@@ -0,0 +1,164 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static CURRENT: Deadline;
+}
+
+/// The error returned when work does not finish before its deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineExceeded;
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadline exceeded")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+/// A point in time by which an async request must be answered.
+///
+/// A deadline is attached to a request with [`Deadline::scope`] and can then
+/// be read anywhere down the call chain with [`Deadline::current`], without
+/// threading it through every signature. Nested scopes can only tighten the
+/// deadline, never extend it, and [`Deadline::slice`] carves out sub-budgets
+/// such as "give the backend 70% of what is left".
+///
+/// The scope is a tokio task-local, so it does not follow work into
+/// `tokio::spawn`; capture `Deadline::current()` and re-scope the spawned
+/// future explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    /// Creates a deadline `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        Deadline {
+            at: Instant::now() + budget,
+        }
+    }
+
+    /// Creates a deadline at the given instant.
+    pub fn at(at: Instant) -> Self {
+        Deadline { at }
+    }
+
+    /// Returns the instant the deadline expires.
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// Returns the time left, or zero once the deadline has passed.
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// Returns `true` once the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    /// Returns an error once the deadline has passed, for cooperative checks
+    /// between units of work.
+    pub fn check(&self) -> Result<(), DeadlineExceeded> {
+        if self.is_expired() {
+            Err(DeadlineExceeded)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns a sub-deadline that expires after `fraction` (clamped to
+    /// `[0, 1]`) of the remaining time.
+    pub fn slice(&self, fraction: f64) -> Deadline {
+        Deadline::after(self.remaining().mul_f64(fraction.clamp(0.0, 1.0)))
+    }
+
+    /// Returns a sub-deadline `reserve` earlier than this one, e.g. to leave
+    /// time for a fallback after a call gives up.
+    pub fn reserve(&self, reserve: Duration) -> Deadline {
+        Deadline::after(self.remaining().saturating_sub(reserve))
+    }
+
+    /// Returns the deadline of the innermost enclosing [`Deadline::scope`].
+    pub fn current() -> Option<Deadline> {
+        CURRENT.try_with(|deadline| *deadline).ok()
+    }
+
+    /// Checks the current scope's deadline, succeeding if there is none.
+    pub fn check_current() -> Result<(), DeadlineExceeded> {
+        Self::current().map_or(Ok(()), |deadline| deadline.check())
+    }
+
+    /// Runs `fut` with this deadline as the current one. If an enclosing scope
+    /// has an earlier deadline, that one stays in force.
+    ///
+    /// Scoping only makes the deadline visible; it does not cancel `fut`. Use
+    /// [`Deadline::run`] to enforce it.
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        let effective = Self::current().map_or(self, |outer| outer.min(self));
+        CURRENT.scope(effective, fut).await
+    }
+
+    /// Runs `fut` within this deadline's scope and cancels it if the deadline
+    /// passes first.
+    pub async fn run<F: Future>(self, fut: F) -> Result<F::Output, DeadlineExceeded> {
+        let effective = Self::current().map_or(self, |outer| outer.min(self));
+        tokio::time::timeout_at(effective.at, CURRENT.scope(effective, fut))
+            .await
+            .map_err(|_| DeadlineExceeded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_verify_deadline_propagation() {
+        let ms = Duration::from_millis;
+        assert!(Deadline::current().is_none());
+        assert!(
+            Deadline::check_current().is_ok(),
+            "No deadline means nothing to miss"
+        );
+
+        let request = Deadline::after(ms(100));
+        request
+            .scope(async move {
+                assert_eq!(Deadline::current(), Some(request));
+
+                let backend = Deadline::current().unwrap().slice(0.7);
+                assert_eq!(backend.remaining(), ms(70));
+
+                let looser = Deadline::after(ms(500));
+                let inner = looser.scope(async { Deadline::current() }).await;
+                assert_eq!(
+                    inner,
+                    Some(request),
+                    "Nested scopes cannot extend the deadline"
+                );
+
+                let nested = backend.run(async { Deadline::current() }).await;
+                assert_eq!(nested, Ok(Some(backend)), "run propagates the sub-deadline");
+
+                let slow = backend.run(tokio::time::sleep(ms(100))).await;
+                assert_eq!(slow, Err(DeadlineExceeded));
+                assert_eq!(
+                    request.remaining(),
+                    ms(30),
+                    "The backend only used its slice"
+                );
+                assert!(Deadline::check_current().is_ok());
+
+                tokio::time::sleep(ms(30)).await;
+                assert_eq!(Deadline::check_current(), Err(DeadlineExceeded));
+            })
+            .await;
+    }
+}
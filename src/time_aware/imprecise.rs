@@ -0,0 +1,281 @@
+use std::time::Duration;
+
+/// One optional refinement step of an imprecise task.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Refinement {
+    /// The processor time the refinement needs.
+    pub duration: Duration,
+    /// The quality gained by completing the refinement.
+    pub quality: f64,
+}
+
+/// A task in Liu's imprecise computation model: a mandatory part that must
+/// finish by the deadline to produce an acceptable result, followed by optional
+/// refinements that improve it and can only run in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpreciseTask {
+    /// The name of the task.
+    pub name: String,
+    /// The earliest time the task may start.
+    pub release: Duration,
+    /// The absolute time by which all scheduled work must finish.
+    pub deadline: Duration,
+    /// The processor time of the mandatory part.
+    pub mandatory: Duration,
+    /// The optional refinements, in the order they must run.
+    pub optional: Vec<Refinement>,
+}
+
+impl ImpreciseTask {
+    /// Creates a new `ImpreciseTask` with no optional refinements.
+    pub fn new(name: &str, release: Duration, deadline: Duration, mandatory: Duration) -> Self {
+        ImpreciseTask {
+            name: name.to_string(),
+            release,
+            deadline,
+            mandatory,
+            optional: Vec::new(),
+        }
+    }
+
+    /// Appends an optional refinement to the task.
+    pub fn with_refinement(mut self, duration: Duration, quality: f64) -> Self {
+        self.optional.push(Refinement { duration, quality });
+        self
+    }
+
+    /// Returns the quality reached if every refinement runs.
+    pub fn max_quality(&self) -> f64 {
+        self.optional.iter().map(|r| r.quality).sum()
+    }
+}
+
+/// A contiguous stretch of processor time given to one task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleSegment {
+    /// The index of the task in the scheduler's task list.
+    pub task: usize,
+    /// When the segment starts.
+    pub start: Duration,
+    /// When the segment ends.
+    pub end: Duration,
+}
+
+/// How much of one task the schedule runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpreciseAllocation {
+    /// The name of the task.
+    pub name: String,
+    /// The number of leading optional refinements that are scheduled.
+    pub refinements: usize,
+    /// The total processor time, mandatory part included.
+    pub execution_time: Duration,
+    /// The quality gained from the scheduled refinements.
+    pub quality: f64,
+}
+
+/// A feasible schedule for a set of imprecise tasks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpreciseSchedule {
+    /// Per-task allocations, in task order.
+    pub allocations: Vec<ImpreciseAllocation>,
+    /// The processor timeline, in time order.
+    pub segments: Vec<ScheduleSegment>,
+    /// The sum of the quality of all tasks.
+    pub total_quality: f64,
+}
+
+/// A uniprocessor scheduler for imprecise tasks.
+///
+/// It first checks that every mandatory part can meet its deadline under EDF,
+/// then hands out the remaining slack one refinement at a time, always taking
+/// the next refinement with the most quality per unit of processor time that
+/// still leaves the task set feasible. Refinements that take no time are
+/// free and go first. A task whose next refinement does not fit receives no
+/// further refinements. The allocation is a heuristic: with differing
+/// deadlines, which refinements fit depends on how EDF interleaves the tasks,
+/// and even under a common deadline choosing whole refinements is a knapsack
+/// problem.
+pub struct ImpreciseScheduler {
+    tasks: Vec<ImpreciseTask>,
+}
+
+impl ImpreciseScheduler {
+    /// Creates a new `ImpreciseScheduler` for the given tasks.
+    pub fn new(tasks: Vec<ImpreciseTask>) -> Self {
+        ImpreciseScheduler { tasks }
+    }
+
+    /// Returns the tasks being scheduled.
+    pub fn tasks(&self) -> &[ImpreciseTask] {
+        &self.tasks
+    }
+
+    /// Builds a schedule, or returns `None` if the mandatory parts alone
+    /// cannot all meet their deadlines.
+    pub fn schedule(&self) -> Option<ImpreciseSchedule> {
+        let mut demand: Vec<Duration> = self.tasks.iter().map(|t| t.mandatory).collect();
+        if !self.is_feasible(&demand) {
+            return None;
+        }
+
+        let mut refinements = vec![0; self.tasks.len()];
+        let mut open = vec![true; self.tasks.len()];
+        while let Some(i) = self.best_candidate(&refinements, &open) {
+            let duration = self.tasks[i].optional[refinements[i]].duration;
+            demand[i] += duration;
+            if self.is_feasible(&demand) {
+                refinements[i] += 1;
+            } else {
+                demand[i] -= duration;
+                open[i] = false;
+            }
+        }
+
+        let allocations: Vec<ImpreciseAllocation> = self
+            .tasks
+            .iter()
+            .zip(&refinements)
+            .zip(&demand)
+            .map(|((task, &count), &execution_time)| ImpreciseAllocation {
+                name: task.name.clone(),
+                refinements: count,
+                execution_time,
+                quality: task.optional[..count].iter().map(|r| r.quality).sum(),
+            })
+            .collect();
+        let total_quality = allocations.iter().map(|a| a.quality).sum();
+        let (_, segments) = self.edf(&demand);
+        Some(ImpreciseSchedule {
+            allocations,
+            segments,
+            total_quality,
+        })
+    }
+
+    /// The open task whose next refinement has the best quality density.
+    fn best_candidate(&self, refinements: &[usize], open: &[bool]) -> Option<usize> {
+        (0..self.tasks.len())
+            .filter(|&i| open[i] && refinements[i] < self.tasks[i].optional.len())
+            .max_by(|&a, &b| {
+                let density = |i: usize| {
+                    let r = self.tasks[i].optional[refinements[i]];
+                    if r.duration.is_zero() {
+                        f64::INFINITY
+                    } else {
+                        r.quality / r.duration.as_secs_f64()
+                    }
+                };
+                density(a).total_cmp(&density(b))
+            })
+    }
+
+    fn is_feasible(&self, demand: &[Duration]) -> bool {
+        self.edf(demand).0
+    }
+
+    /// Runs preemptive EDF with the given per-task demand, returning whether
+    /// every task finished by its deadline and the resulting timeline.
+    fn edf(&self, demand: &[Duration]) -> (bool, Vec<ScheduleSegment>) {
+        let mut remaining = demand.to_vec();
+        let mut segments: Vec<ScheduleSegment> = Vec::new();
+        let mut feasible = true;
+        let mut now = Duration::ZERO;
+        loop {
+            let pending = (0..self.tasks.len()).filter(|&i| !remaining[i].is_zero());
+            let next_release = pending
+                .clone()
+                .map(|i| self.tasks[i].release)
+                .filter(|&r| r > now)
+                .min();
+            let ready = pending
+                .filter(|&i| self.tasks[i].release <= now)
+                .min_by_key(|&i| (self.tasks[i].deadline, i));
+            let Some(i) = ready else {
+                match next_release {
+                    Some(release) => {
+                        now = release;
+                        continue;
+                    }
+                    None => break,
+                }
+            };
+
+            let run = match next_release {
+                Some(release) => remaining[i].min(release - now),
+                None => remaining[i],
+            };
+            match segments.last_mut() {
+                Some(last) if last.task == i && last.end == now => last.end += run,
+                _ => segments.push(ScheduleSegment {
+                    task: i,
+                    start: now,
+                    end: now + run,
+                }),
+            }
+            now += run;
+            remaining[i] -= run;
+            if remaining[i].is_zero() && now > self.tasks[i].deadline {
+                feasible = false;
+            }
+        }
+        (feasible, segments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_imprecise_computation() {
+        let ms = Duration::from_millis;
+        let tasks = vec![
+            ImpreciseTask::new("render", ms(0), ms(10), ms(2))
+                .with_refinement(ms(3), 5.0)
+                .with_refinement(ms(3), 1.0),
+            ImpreciseTask::new("physics", ms(0), ms(6), ms(2))
+                .with_refinement(ms(2), 4.0)
+                .with_refinement(ms(2), 3.0),
+        ];
+        let scheduler = ImpreciseScheduler::new(tasks);
+        let schedule = scheduler.schedule().unwrap();
+
+        assert_eq!(schedule.allocations[0].refinements, 1);
+        assert_eq!(schedule.allocations[1].refinements, 1);
+        assert_eq!(schedule.total_quality, 9.0);
+        for (task, allocation) in scheduler.tasks().iter().zip(&schedule.allocations) {
+            assert!(
+                allocation.execution_time >= task.mandatory,
+                "Mandatory parts always run"
+            );
+        }
+        for segment in &schedule.segments {
+            assert!(segment.end <= scheduler.tasks()[segment.task].deadline);
+        }
+        let busy: Duration = schedule.segments.iter().map(|s| s.end - s.start).sum();
+        assert_eq!(busy, ms(9));
+
+        let overloaded = ImpreciseScheduler::new(vec![
+            ImpreciseTask::new("a", ms(0), ms(5), ms(4)),
+            ImpreciseTask::new("b", ms(0), ms(5), ms(4)),
+        ]);
+        assert!(
+            overloaded.schedule().is_none(),
+            "Infeasible mandatory parts are rejected"
+        );
+
+        // A refinement that takes no time is free, even with no quality, so it
+        // is taken first; the 3ms of slack then go to the denser refinement.
+        let free = ImpreciseScheduler::new(vec![
+            ImpreciseTask::new("cached", ms(0), ms(5), ms(1))
+                .with_refinement(ms(0), 0.0)
+                .with_refinement(ms(2), 1.0),
+            ImpreciseTask::new("fresh", ms(0), ms(5), ms(1)).with_refinement(ms(2), 3.0),
+        ]);
+        let schedule = free.schedule().unwrap();
+        assert_eq!(schedule.allocations[0].refinements, 1);
+        assert_eq!(schedule.allocations[1].refinements, 1);
+        assert_eq!(schedule.total_quality, 3.0);
+    }
+}
@@ -0,0 +1,384 @@
+use super::AnytimeResult;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Sub-buckets per power of two is `2^SUB_BUCKET_BITS`, giving a worst-case
+/// relative error of `2 / 2^SUB_BUCKET_BITS` (about 1.6%).
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+const HALF_SUB_BUCKETS: usize = SUB_BUCKETS / 2;
+const BUCKET_COUNT: usize = SUB_BUCKETS + (64 - SUB_BUCKET_BITS as usize) * HALF_SUB_BUCKETS;
+
+/// The `le` boundaries, in seconds, used when exporting histograms.
+pub const DEFAULT_EXPORT_BUCKETS: [f64; 17] = [
+    1e-6, 1e-5, 1e-4, 5e-4, 1e-3, 2.5e-3, 5e-3, 1e-2, 2.5e-2, 5e-2, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+    10.0,
+];
+
+/// A lock-free latency histogram in the style of HdrHistogram.
+///
+/// Values are recorded in nanoseconds into log-linear buckets: exact below
+/// 128ns, then 64 sub-buckets per power of two, so quantiles are accurate to
+/// within about 1.6% over the full `u64` range in a fixed 30KB of counters.
+pub struct LatencyHistogram {
+    counts: Vec<AtomicU64>,
+    total: AtomicU64,
+    sum_nanos: AtomicU64,
+    max_nanos: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        LatencyHistogram {
+            counts: (0..BUCKET_COUNT).map(|_| AtomicU64::new(0)).collect(),
+            total: AtomicU64::new(0),
+            sum_nanos: AtomicU64::new(0),
+            max_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one value.
+    pub fn record(&self, value: Duration) {
+        let nanos = value.as_nanos().min(u64::MAX as u128) as u64;
+        self.counts[bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Returns the number of recorded values.
+    pub fn count(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Returns the sum of all recorded values.
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Returns the largest recorded value, exactly.
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Returns the value at quantile `q` (in `[0, 1]`), reported as the upper
+    /// end of its bucket, or `None` if nothing was recorded.
+    pub fn value_at_quantile(&self, q: f64) -> Option<Duration> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            seen += count.load(Ordering::Relaxed);
+            if seen >= rank {
+                let value = bucket_upper(idx).min(self.max_nanos.load(Ordering::Relaxed));
+                return Some(Duration::from_nanos(value));
+            }
+        }
+        Some(self.max())
+    }
+
+    /// Returns the number of values at or below `bound`, to bucket precision.
+    pub fn count_at_or_below(&self, bound: Duration) -> u64 {
+        let bound = bound.as_nanos().min(u64::MAX as u128) as u64;
+        self.counts
+            .iter()
+            .enumerate()
+            .take_while(|(idx, _)| bucket_upper(*idx) <= bound)
+            .map(|(_, count)| count.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+fn bucket_index(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS as u64 {
+        return nanos as usize;
+    }
+    let msb = 63 - nanos.leading_zeros();
+    let group = (msb - SUB_BUCKET_BITS + 1) as usize;
+    let sub = (nanos >> group) as usize - HALF_SUB_BUCKETS;
+    SUB_BUCKETS + (group - 1) * HALF_SUB_BUCKETS + sub
+}
+
+/// The largest value that maps to bucket `idx`.
+fn bucket_upper(idx: usize) -> u64 {
+    if idx < SUB_BUCKETS {
+        return idx as u64;
+    }
+    let group = (idx - SUB_BUCKETS) / HALF_SUB_BUCKETS + 1;
+    let sub = (idx - SUB_BUCKETS) % HALF_SUB_BUCKETS + HALF_SUB_BUCKETS;
+    let upper = ((sub as u128 + 1) << group) - 1;
+    upper.min(u64::MAX as u128) as u64
+}
+
+/// An opt-in recorder of how deadline-bounded runs behave in production.
+///
+/// It tracks how many runs completed before their deadline, the latency of
+/// every run, and for interrupted runs the overshoot: how long after the
+/// deadline the check that stopped the run actually fired. Recorders are
+/// thread-safe and are typically shared through an `Arc`.
+///
+/// Only `AnytimeQuicksort::with_metrics` records runs by itself. The other
+/// sorts, the parallel sort and `anytime::run_until` report nothing unless
+/// their callers pass the results to `record_run` or `record_cutoff`.
+pub struct TimeAwareMetrics {
+    algorithm: String,
+    runs: AtomicU64,
+    completed: AtomicU64,
+    latency: LatencyHistogram,
+    overshoot: LatencyHistogram,
+}
+
+impl TimeAwareMetrics {
+    /// Creates a recorder whose metrics carry the label `algorithm="<algorithm>"`.
+    pub fn new(algorithm: &str) -> Self {
+        TimeAwareMetrics {
+            algorithm: algorithm.to_string(),
+            runs: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            latency: LatencyHistogram::new(),
+            overshoot: LatencyHistogram::new(),
+        }
+    }
+
+    /// Records one run that was given `deadline`, took `elapsed` and either
+    /// completed or was cut off. For a run that was cut off, `elapsed` stands
+    /// in for when the deadline check fired; use `record_cutoff` if that
+    /// reading is known.
+    pub fn record_run(&self, deadline: Duration, elapsed: Duration, completed: bool) {
+        if completed {
+            self.runs.fetch_add(1, Ordering::Relaxed);
+            self.completed.fetch_add(1, Ordering::Relaxed);
+            self.latency.record(elapsed);
+        } else {
+            self.record_cutoff(deadline, elapsed, elapsed);
+        }
+    }
+
+    /// Records one run that was given `deadline`, took `elapsed` and was cut
+    /// off by a deadline check that read `fired` since the run started.
+    pub fn record_cutoff(&self, deadline: Duration, elapsed: Duration, fired: Duration) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.latency.record(elapsed);
+        self.overshoot.record(fired.saturating_sub(deadline));
+    }
+
+    /// Records an anytime sort that was given `deadline`.
+    pub fn record_result(&self, deadline: Duration, result: &AnytimeResult) {
+        self.record_run(deadline, result.elapsed, result.completed);
+    }
+
+    /// Returns the number of runs recorded.
+    pub fn runs(&self) -> u64 {
+        self.runs.load(Ordering::Relaxed)
+    }
+
+    /// Returns the fraction of runs that completed before their deadline, or
+    /// `1.0` if none were recorded.
+    pub fn completion_ratio(&self) -> f64 {
+        let runs = self.runs();
+        if runs == 0 {
+            1.0
+        } else {
+            self.completed.load(Ordering::Relaxed) as f64 / runs as f64
+        }
+    }
+
+    /// Returns the latency histogram of all runs.
+    pub fn latency(&self) -> &LatencyHistogram {
+        &self.latency
+    }
+
+    /// Returns the deadline overshoot histogram of interrupted runs.
+    pub fn overshoot(&self) -> &LatencyHistogram {
+        &self.overshoot
+    }
+
+    /// Renders this recorder in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        render_prometheus(&[self])
+    }
+}
+
+/// Renders several recorders in the Prometheus text exposition format, one
+/// metric family per measurement with the recorders distinguished by their
+/// `algorithm` label. Histograms use [`DEFAULT_EXPORT_BUCKETS`].
+pub fn render_prometheus(recorders: &[&TimeAwareMetrics]) -> String {
+    let mut out = String::new();
+    let label = |m: &TimeAwareMetrics| format!("algorithm=\"{}\"", escape_label(&m.algorithm));
+
+    family(
+        &mut out,
+        "time_aware_runs_total",
+        "counter",
+        "Deadline-bounded runs recorded.",
+    );
+    for m in recorders {
+        let _ = writeln!(out, "time_aware_runs_total{{{}}} {}", label(m), m.runs());
+    }
+    family(
+        &mut out,
+        "time_aware_completed_total",
+        "counter",
+        "Runs that completed before their deadline.",
+    );
+    for m in recorders {
+        let completed = m.completed.load(Ordering::Relaxed);
+        let _ = writeln!(
+            out,
+            "time_aware_completed_total{{{}}} {}",
+            label(m),
+            completed
+        );
+    }
+    family(
+        &mut out,
+        "time_aware_completion_ratio",
+        "gauge",
+        "Fraction of runs that completed before their deadline.",
+    );
+    for m in recorders {
+        let _ = writeln!(
+            out,
+            "time_aware_completion_ratio{{{}}} {}",
+            label(m),
+            m.completion_ratio()
+        );
+    }
+
+    let histograms: [(&str, &str, HistogramOf); 2] = [
+        (
+            "time_aware_latency_seconds",
+            "Run latency.",
+            TimeAwareMetrics::latency,
+        ),
+        (
+            "time_aware_deadline_overshoot_seconds",
+            "Time between the deadline and the check that stopped an interrupted run.",
+            TimeAwareMetrics::overshoot,
+        ),
+    ];
+    for (name, help, histogram_of) in histograms {
+        family(&mut out, name, "histogram", help);
+        for m in recorders {
+            let histogram = histogram_of(m);
+            for le in DEFAULT_EXPORT_BUCKETS {
+                let count = histogram.count_at_or_below(Duration::from_secs_f64(le));
+                let _ = writeln!(out, "{name}_bucket{{{},le=\"{le}\"}} {count}", label(m));
+            }
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{},le=\"+Inf\"}} {}",
+                label(m),
+                histogram.count()
+            );
+            let _ = writeln!(
+                out,
+                "{name}_sum{{{}}} {}",
+                label(m),
+                histogram.sum().as_secs_f64()
+            );
+            let _ = writeln!(out, "{name}_count{{{}}} {}", label(m), histogram.count());
+        }
+    }
+    out
+}
+
+type HistogramOf = fn(&TimeAwareMetrics) -> &LatencyHistogram;
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_aware::clock::VirtualClock;
+    use crate::time_aware::AnytimeQuicksort;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_verify_deadline_telemetry() {
+        let histogram = LatencyHistogram::new();
+        for micros in 1..=10_000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        let p50 = histogram.value_at_quantile(0.5).unwrap().as_secs_f64();
+        let p99 = histogram.value_at_quantile(0.99).unwrap().as_secs_f64();
+        assert!((p50 - 5e-3).abs() / 5e-3 < 0.02, "p50 {p50} should be ~5ms");
+        assert!(
+            (p99 - 9.9e-3).abs() / 9.9e-3 < 0.02,
+            "p99 {p99} should be ~9.9ms"
+        );
+        assert_eq!(histogram.max(), Duration::from_millis(10));
+        for idx in [0, 127, 128, 200, 3_000, BUCKET_COUNT - 1] {
+            assert_eq!(
+                bucket_index(bucket_upper(idx)),
+                idx,
+                "Bucket bounds should round-trip"
+            );
+        }
+
+        let metrics = TimeAwareMetrics::new("quicksort");
+        let deadline = Duration::from_millis(2);
+        metrics.record_run(deadline, Duration::from_millis(1), true);
+        metrics.record_run(deadline, Duration::from_micros(2_300), false);
+        assert_eq!(metrics.completion_ratio(), 0.5);
+        let overshoot = metrics.overshoot().value_at_quantile(1.0).unwrap();
+        assert_eq!(overshoot, Duration::from_micros(300));
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE time_aware_latency_seconds histogram"));
+        assert!(text.contains("time_aware_runs_total{algorithm=\"quicksort\"} 2"));
+        assert!(text.contains("time_aware_completion_ratio{algorithm=\"quicksort\"} 0.5"));
+        assert!(text.contains(
+            "time_aware_deadline_overshoot_seconds_bucket{algorithm=\"quicksort\",le=\"0.0005\"} 1"
+        ));
+        assert!(text
+            .contains("time_aware_latency_seconds_bucket{algorithm=\"quicksort\",le=\"+Inf\"} 2"));
+
+        // One virtual microsecond per clock read: the check that stops the sort
+        // fires exactly at the deadline, and the final reading a tick later
+        // counts towards latency but not overshoot.
+        let metrics = Arc::new(TimeAwareMetrics::new("quicksort"));
+        let clock = VirtualClock::stepping(Duration::from_micros(1));
+        let mut sorter = AnytimeQuicksort::with_clock(Duration::from_micros(100), &clock)
+            .with_metrics(metrics.clone());
+        let mut arr: Vec<i32> = (0..1_000).rev().collect();
+        sorter.sort(&mut arr);
+        sorter.sort(&mut [3, 1, 2]);
+        assert_eq!(metrics.runs(), 2);
+        assert_eq!(metrics.completion_ratio(), 0.5);
+        assert_eq!(metrics.overshoot().count(), 1);
+        assert_eq!(metrics.overshoot().max(), Duration::ZERO);
+        assert_eq!(metrics.latency().max(), Duration::from_micros(101));
+
+        // A sort whose last check fires after the work is done still counts
+        // as completed.
+        let metrics = Arc::new(TimeAwareMetrics::new("quicksort"));
+        let clock = VirtualClock::stepping(Duration::from_micros(1));
+        let mut sorter = AnytimeQuicksort::with_clock(Duration::from_micros(3), &clock)
+            .with_metrics(metrics.clone());
+        assert!(sorter.sort(&mut [2, 1]).completed);
+        assert_eq!(metrics.completion_ratio(), 1.0);
+        assert_eq!(metrics.overshoot().count(), 0);
+    }
+}
@@ -0,0 +1,240 @@
+use super::clock::{Clock, MonotonicClock};
+use super::{sortedness, AnytimeResult};
+use std::cmp::Ordering;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::time::Duration;
+
+/// A parallel quicksort that stops all of its workers at one shared deadline.
+///
+/// After each partitioning pass the two sides are independent, so they are
+/// forked onto rayon's thread pool with `rayon::join` until they shrink below
+/// a threshold and are finished sequentially. Every worker checks the same
+/// deadline; the first one to see it pass raises a shared flag so the others
+/// stop without consulting the clock again. Partitioning only ever swaps
+/// elements within its own range, so an interrupted sort always leaves a
+/// permutation of the input behind.
+///
+/// The sort runs on the current rayon pool: the global one by default, or a
+/// custom pool when called inside `ThreadPool::install`.
+pub struct ParallelAnytimeQuicksort<C = MonotonicClock> {
+    deadline: Duration,
+    clock: C,
+    min_parallel_len: usize,
+}
+
+impl ParallelAnytimeQuicksort {
+    /// Creates a new `ParallelAnytimeQuicksort` with a specified deadline in milliseconds.
+    pub fn new(deadline_ms: u64) -> Self {
+        Self::with_clock(Duration::from_millis(deadline_ms), MonotonicClock::new())
+    }
+}
+
+impl<C: Clock + Sync> ParallelAnytimeQuicksort<C> {
+    /// The default length below which partitions are sorted sequentially.
+    pub const DEFAULT_MIN_PARALLEL_LEN: usize = 4096;
+
+    /// Creates a new `ParallelAnytimeQuicksort` that measures its deadline with `clock`.
+    pub fn with_clock(deadline: Duration, clock: C) -> Self {
+        ParallelAnytimeQuicksort {
+            deadline,
+            clock,
+            min_parallel_len: Self::DEFAULT_MIN_PARALLEL_LEN,
+        }
+    }
+
+    /// Sets the length below which partitions are not forked any further.
+    pub fn with_min_parallel_len(mut self, min_parallel_len: usize) -> Self {
+        self.min_parallel_len = min_parallel_len.max(2);
+        self
+    }
+
+    /// Sorts the given array until the deadline is met.
+    pub fn sort<T: Ord + Send>(&self, arr: &mut [T]) -> AnytimeResult {
+        self.sort_by(arr, T::cmp)
+    }
+
+    /// Sorts the given array with a comparator function until the deadline is met.
+    ///
+    /// As with `AnytimeQuicksort`, the sortedness score of an interrupted sort
+    /// is computed after the deadline, sequentially.
+    pub fn sort_by<T, F>(&self, arr: &mut [T], compare: F) -> AnytimeResult
+    where
+        T: Send,
+        F: Fn(&T, &T) -> Ordering + Sync,
+    {
+        let start = self.clock.now();
+        let job = SortJob {
+            clock: &self.clock,
+            start,
+            deadline: self.deadline,
+            expired: AtomicBool::new(false),
+            finalized_partitions: AtomicUsize::new(0),
+            min_parallel_len: self.min_parallel_len,
+            compare: &compare,
+        };
+        // Beyond this depth the pivots are poor, so recursion stops forking.
+        let max_depth = 2 * (usize::BITS - arr.len().leading_zeros()) as usize;
+        let completed = job.sort(arr, max_depth);
+        let elapsed = self.clock.now().saturating_sub(start);
+        let sortedness = if completed {
+            1.0
+        } else {
+            sortedness(arr, &compare)
+        };
+
+        AnytimeResult {
+            elapsed,
+            completed,
+            finalized_partitions: job.finalized_partitions.into_inner(),
+            sortedness,
+        }
+    }
+}
+
+/// The state shared by every worker of one parallel sort.
+struct SortJob<'a, C, F> {
+    clock: &'a C,
+    start: Duration,
+    deadline: Duration,
+    expired: AtomicBool,
+    finalized_partitions: AtomicUsize,
+    min_parallel_len: usize,
+    compare: &'a F,
+}
+
+impl<C, F> SortJob<'_, C, F>
+where
+    C: Clock + Sync,
+{
+    fn time_exceeded(&self) -> bool {
+        if self.expired.load(AtomicOrdering::Relaxed) {
+            return true;
+        }
+        let exceeded = self.clock.now().saturating_sub(self.start) >= self.deadline;
+        if exceeded {
+            self.expired.store(true, AtomicOrdering::Relaxed);
+        }
+        exceeded
+    }
+
+    /// Sorts `arr`, returning `true` if it is fully sorted.
+    fn sort<T>(&self, arr: &mut [T], depth: usize) -> bool
+    where
+        T: Send,
+        F: Fn(&T, &T) -> Ordering + Sync,
+    {
+        if arr.len() < self.min_parallel_len || depth == 0 {
+            return self.sort_sequential(arr);
+        }
+        if self.time_exceeded() {
+            return false;
+        }
+        let Some(p) = self.partition(arr) else {
+            return false;
+        };
+        self.finalized_partitions
+            .fetch_add(1, AtomicOrdering::Relaxed);
+        let (left, rest) = arr.split_at_mut(p);
+        let right = &mut rest[1..];
+        let (left, right) = rayon::join(
+            || self.sort(left, depth - 1),
+            || self.sort(right, depth - 1),
+        );
+        left && right
+    }
+
+    /// Finishes `arr` on the current thread with an explicit work stack.
+    fn sort_sequential<T>(&self, arr: &mut [T]) -> bool
+    where
+        F: Fn(&T, &T) -> Ordering,
+    {
+        let mut pending = vec![(0, arr.len())];
+        while let Some((lo, hi)) = pending.pop() {
+            if hi - lo <= 1 {
+                continue;
+            }
+            if self.time_exceeded() {
+                return false;
+            }
+            let Some(p) = self.partition(&mut arr[lo..hi]) else {
+                return false;
+            };
+            self.finalized_partitions
+                .fetch_add(1, AtomicOrdering::Relaxed);
+            let p = lo + p;
+            // Larger side first so the stack stays O(log n) deep.
+            if p - lo > hi - p - 1 {
+                pending.push((lo, p));
+                pending.push((p + 1, hi));
+            } else {
+                pending.push((p + 1, hi));
+                pending.push((lo, p));
+            }
+        }
+        true
+    }
+
+    /// Lomuto partition of `arr` around its last element. Returns the pivot's
+    /// final index, or `None` if the deadline fired mid-pass.
+    fn partition<T>(&self, arr: &mut [T]) -> Option<usize>
+    where
+        F: Fn(&T, &T) -> Ordering,
+    {
+        let pivot = arr.len() - 1;
+        let mut i = 0;
+        for j in 0..pivot {
+            if self.time_exceeded() {
+                return None;
+            }
+            if (self.compare)(&arr[j], &arr[pivot]) == Ordering::Less {
+                arr.swap(i, j);
+                i += 1;
+            }
+        }
+        arr.swap(i, pivot);
+        Some(i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_aware::clock::VirtualClock;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_verify_parallel_anytime_sort() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let input: Vec<u64> = (0..100_000).map(|_| rng.gen()).collect();
+
+        let mut arr = input.clone();
+        let sorter = ParallelAnytimeQuicksort::new(60_000).with_min_parallel_len(1024);
+        let result = sorter.sort(&mut arr);
+        assert!(
+            result.completed,
+            "A generous deadline should let the sort finish"
+        );
+        assert!(arr.windows(2).all(|w| w[0] <= w[1]));
+        assert!(result.finalized_partitions > 0);
+
+        let clock = VirtualClock::stepping(Duration::from_micros(1));
+        let mut arr = input.clone();
+        let sorter = ParallelAnytimeQuicksort::with_clock(Duration::from_millis(50), &clock)
+            .with_min_parallel_len(1024);
+        let result = sorter.sort_by(&mut arr, |a, b| a.cmp(b));
+        assert!(
+            !result.completed,
+            "50k clock reads cannot sort 100k elements"
+        );
+        assert!(result.sortedness > 0.5 && result.sortedness < 1.0);
+
+        let (mut before, mut after) = (input, arr);
+        before.sort_unstable();
+        after.sort_unstable();
+        assert_eq!(
+            before, after,
+            "An interrupted sort must leave a permutation of the input"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use super::anytime::{run_until, Anytime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The quality an anytime algorithm reached with one time budget, aggregated
+/// over repeated trials.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProfilePoint {
+    /// The time budget the algorithm was given.
+    pub budget: Duration,
+    /// The mean quality over all trials.
+    pub mean_quality: f64,
+    /// The standard deviation of the quality over all trials.
+    pub std_dev: f64,
+    /// The worst quality observed.
+    pub min_quality: f64,
+    /// The number of trials.
+    pub trials: usize,
+}
+
+/// A conditional performance profile in the sense of Dean & Boddy: the
+/// quality an anytime algorithm is expected to reach, conditioned on the
+/// input size and the time it is given.
+///
+/// Profiles are expensive to build, so they can be saved to and loaded from
+/// JSON files.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PerformanceProfile {
+    points: BTreeMap<usize, Vec<ProfilePoint>>,
+}
+
+impl PerformanceProfile {
+    /// Creates an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `point` for input size `n`, keeping the points ordered by budget.
+    pub fn insert(&mut self, n: usize, point: ProfilePoint) {
+        let points = self.points.entry(n).or_default();
+        let idx = points.partition_point(|p| p.budget < point.budget);
+        points.insert(idx, point);
+    }
+
+    /// Returns the input sizes the profile covers.
+    pub fn sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.points.keys().copied()
+    }
+
+    /// Returns the points recorded for input size `n`, ordered by budget.
+    pub fn points(&self, n: usize) -> Option<&[ProfilePoint]> {
+        self.points.get(&n).map(Vec::as_slice)
+    }
+
+    /// Returns the expected quality for input size `n` with `budget`.
+    ///
+    /// Budgets are interpolated linearly between profiled points and clamped
+    /// at the ends. Sizes between two profiled sizes are interpolated on a log
+    /// scale; sizes outside the profiled range return `None`.
+    pub fn expected_quality(&self, n: usize, budget: Duration) -> Option<f64> {
+        if let Some(points) = self.points.get(&n) {
+            return quality_at(points, budget);
+        }
+        let (lo, lo_points) = self.points.range(..n).next_back()?;
+        let (hi, hi_points) = self.points.range(n..).next()?;
+        let (q_lo, q_hi) = (
+            quality_at(lo_points, budget)?,
+            quality_at(hi_points, budget)?,
+        );
+        let w = log_position(*lo, *hi, n);
+        Some(q_lo + (q_hi - q_lo) * w)
+    }
+
+    /// Returns the smallest budget expected to reach `target` quality for
+    /// input size `n`, e.g. "how long for 90% quality on n = 1e6".
+    ///
+    /// For profiled sizes the answer is interpolated between budgets. For
+    /// other sizes the required time is assumed to follow a power law in `n`,
+    /// fitted between the two nearest profiled sizes that reach the target, and
+    /// extrapolated beyond the profiled range. Returns `None` if the profile
+    /// never reaches the target.
+    pub fn time_for_quality(&self, n: usize, target: f64) -> Option<Duration> {
+        if let Some(points) = self.points.get(&n) {
+            return time_to_reach(points, target);
+        }
+        let known: Vec<(usize, Duration)> = self
+            .points
+            .iter()
+            .filter_map(|(&size, points)| time_to_reach(points, target).map(|t| (size, t)))
+            .filter(|&(size, _)| size > 0)
+            .collect();
+        if known.len() < 2 || n == 0 {
+            return None;
+        }
+        // The nearest pair bracketing n, or the last/first pair to extrapolate.
+        let upper = known
+            .partition_point(|&(size, _)| size < n)
+            .clamp(1, known.len() - 1);
+        let ((n0, t0), (n1, t1)) = (known[upper - 1], known[upper]);
+        let (t0, t1) = (
+            t0.as_secs_f64().max(f64::MIN_POSITIVE),
+            t1.as_secs_f64().max(f64::MIN_POSITIVE),
+        );
+        let exponent = (t1 / t0).ln() / (n1 as f64 / n0 as f64).ln();
+        let seconds = t0 * (n as f64 / n0 as f64).powf(exponent);
+        seconds
+            .is_finite()
+            .then(|| Duration::from_secs_f64(seconds))
+    }
+
+    /// Writes the profile to `path` as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Reads a profile previously written by [`PerformanceProfile::save`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Builds performance profiles by running an anytime algorithm at a sweep of
+/// input sizes and time budgets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profiler {
+    /// The input sizes to profile.
+    pub sizes: Vec<usize>,
+    /// The time budgets to try at each size.
+    pub budgets: Vec<Duration>,
+    /// The number of runs per size and budget.
+    pub trials: usize,
+}
+
+impl Profiler {
+    /// Builds a profile from `run`, which is called as `run(n, budget)` for
+    /// every size, budget and trial and returns the quality it reached.
+    ///
+    /// Taking a closure lets any deadline-bounded computation be profiled,
+    /// e.g. an `AnytimeQuicksort` returning its `sortedness`.
+    pub fn profile<F>(&self, mut run: F) -> PerformanceProfile
+    where
+        F: FnMut(usize, Duration) -> f64,
+    {
+        let mut profile = PerformanceProfile::new();
+        for &n in &self.sizes {
+            for &budget in &self.budgets {
+                let qualities: Vec<f64> = (0..self.trials).map(|_| run(n, budget)).collect();
+                if let Some(point) = aggregate(budget, &qualities) {
+                    profile.insert(n, point);
+                }
+            }
+        }
+        profile
+    }
+
+    /// Builds a profile for an [`Anytime`] implementation, creating a fresh
+    /// instance for input size `n` with `factory(n)` before every run.
+    pub fn profile_anytime<A, F>(&self, mut factory: F) -> PerformanceProfile
+    where
+        A: Anytime,
+        F: FnMut(usize) -> A,
+    {
+        self.profile(|n, budget| {
+            let mut algorithm = factory(n);
+            run_until(&mut algorithm, Instant::now() + budget).quality
+        })
+    }
+}
+
+fn aggregate(budget: Duration, qualities: &[f64]) -> Option<ProfilePoint> {
+    if qualities.is_empty() {
+        return None;
+    }
+    let trials = qualities.len();
+    let mean_quality = qualities.iter().sum::<f64>() / trials as f64;
+    let variance = qualities
+        .iter()
+        .map(|q| (q - mean_quality).powi(2))
+        .sum::<f64>()
+        / trials as f64;
+    Some(ProfilePoint {
+        budget,
+        mean_quality,
+        std_dev: variance.sqrt(),
+        min_quality: qualities.iter().copied().fold(f64::INFINITY, f64::min),
+        trials,
+    })
+}
+
+/// Mean quality at `budget`, interpolated between the surrounding points.
+fn quality_at(points: &[ProfilePoint], budget: Duration) -> Option<f64> {
+    let first = points.first()?;
+    let idx = points.partition_point(|p| p.budget < budget);
+    if idx == 0 {
+        return Some(first.mean_quality);
+    }
+    let Some(hi) = points.get(idx) else {
+        return points.last().map(|p| p.mean_quality);
+    };
+    let lo = &points[idx - 1];
+    let span = (hi.budget - lo.budget).as_secs_f64();
+    let w = if span > 0.0 {
+        (budget - lo.budget).as_secs_f64() / span
+    } else {
+        1.0
+    };
+    Some(lo.mean_quality + (hi.mean_quality - lo.mean_quality) * w)
+}
+
+/// The first budget at which the mean quality reaches `target`, interpolated
+/// from the point before it.
+fn time_to_reach(points: &[ProfilePoint], target: f64) -> Option<Duration> {
+    let idx = points.iter().position(|p| p.mean_quality >= target)?;
+    if idx == 0 {
+        return Some(points[0].budget);
+    }
+    let (lo, hi) = (&points[idx - 1], &points[idx]);
+    let w = (target - lo.mean_quality) / (hi.mean_quality - lo.mean_quality);
+    Some(lo.budget + (hi.budget - lo.budget).mul_f64(w.clamp(0.0, 1.0)))
+}
+
+fn log_position(lo: usize, hi: usize, n: usize) -> f64 {
+    let (lo, hi, n) = (
+        (lo.max(1) as f64).ln(),
+        (hi.max(1) as f64).ln(),
+        (n.max(1) as f64).ln(),
+    );
+    if hi > lo {
+        (n - lo) / (hi - lo)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_aware::anytime::AnytimeTopK;
+
+    #[test]
+    fn test_verify_performance_profile() {
+        // A synthetic algorithm whose quality approaches 1 exponentially, with a
+        // time constant of 1µs per element: 90% quality needs n * ln(10) µs.
+        let quality =
+            |n: usize, budget: Duration| 1.0 - (-budget.as_secs_f64() / (n as f64 * 1e-6)).exp();
+        let profiler = Profiler {
+            sizes: vec![1_000, 10_000, 100_000],
+            budgets: (1..=100)
+                .map(|i| Duration::from_micros(i * 3_000))
+                .collect(),
+            trials: 2,
+        };
+        let profile = profiler.profile(quality);
+
+        let seconds_for_90 = |n: f64| n * 1e-6 * 10f64.ln();
+        let t = profile
+            .time_for_quality(100_000, 0.9)
+            .unwrap()
+            .as_secs_f64();
+        assert!((t - seconds_for_90(1e5)).abs() / seconds_for_90(1e5) < 0.05);
+        let t = profile
+            .time_for_quality(1_000_000, 0.9)
+            .unwrap()
+            .as_secs_f64();
+        assert!(
+            (t - seconds_for_90(1e6)).abs() / seconds_for_90(1e6) < 0.05,
+            "Extrapolated {t}s"
+        );
+        assert!(profile.time_for_quality(1_000, 1.5).is_none());
+
+        let q = profile
+            .expected_quality(10_000, Duration::from_millis(30))
+            .unwrap();
+        assert!((q - quality(10_000, Duration::from_millis(30))).abs() < 0.01);
+
+        let path = std::env::temp_dir().join(format!("profile-{}.json", std::process::id()));
+        profile.save(&path).unwrap();
+        let loaded = PerformanceProfile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            loaded, profile,
+            "A profile should survive a round trip to disk"
+        );
+
+        let profiler = Profiler {
+            sizes: vec![100],
+            budgets: vec![Duration::from_secs(5)],
+            trials: 1,
+        };
+        let profile =
+            profiler.profile_anytime(|n| AnytimeTopK::new((0..n).collect::<Vec<_>>(), 5, 10));
+        assert_eq!(profile.points(100).unwrap()[0].mean_quality, 1.0);
+    }
+}
@@ -0,0 +1,448 @@
+use super::clock::Clock;
+use super::WcetAnalyzer;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+
+/// How the jobs of a real-time task are released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrivalPattern {
+    /// A job is released exactly every period.
+    Periodic,
+    /// Jobs are separated by at least the period, plus a random extra delay of
+    /// up to `max_extra_delay`.
+    Sporadic {
+        /// The largest additional gap between consecutive releases.
+        max_extra_delay: Duration,
+    },
+}
+
+/// A recurring task with a hard relative deadline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RealTimeTask {
+    /// The name of the task.
+    pub name: String,
+    /// The worst-case execution time of each job.
+    pub wcet: Duration,
+    /// The period, or minimum inter-arrival time for sporadic tasks.
+    pub period: Duration,
+    /// The relative deadline of each job.
+    pub deadline: Duration,
+    /// The release time of the first job.
+    pub offset: Duration,
+    /// How jobs are released.
+    pub arrival: ArrivalPattern,
+}
+
+impl RealTimeTask {
+    /// Creates a periodic task whose deadline equals its period.
+    pub fn periodic(name: &str, wcet: Duration, period: Duration) -> Self {
+        RealTimeTask {
+            name: name.to_string(),
+            wcet,
+            period,
+            deadline: period,
+            offset: Duration::ZERO,
+            arrival: ArrivalPattern::Periodic,
+        }
+    }
+
+    /// Creates a sporadic task whose deadline equals its minimum inter-arrival time.
+    pub fn sporadic(
+        name: &str,
+        wcet: Duration,
+        min_interarrival: Duration,
+        max_extra_delay: Duration,
+    ) -> Self {
+        RealTimeTask {
+            arrival: ArrivalPattern::Sporadic { max_extra_delay },
+            ..Self::periodic(name, wcet, min_interarrival)
+        }
+    }
+
+    /// Creates a periodic task whose WCET is the pWCET estimated by `analyzer`
+    /// at `exceedance_probability`, or `None` if the analyzer cannot fit one.
+    pub fn from_wcet_analyzer<C: Clock>(
+        name: &str,
+        analyzer: &WcetAnalyzer<C>,
+        exceedance_probability: f64,
+        period: Duration,
+    ) -> Option<Self> {
+        let wcet_ms = analyzer.pwcet(exceedance_probability)?;
+        Some(Self::periodic(
+            name,
+            Duration::from_secs_f64(wcet_ms.max(0.0) / 1000.0),
+            period,
+        ))
+    }
+
+    /// Sets a relative deadline different from the period.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Returns the fraction of the processor the task needs, `wcet / period`.
+    pub fn utilization(&self) -> f64 {
+        self.wcet.as_secs_f64() / self.period.as_secs_f64()
+    }
+}
+
+/// A preemptive uniprocessor scheduling policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulingPolicy {
+    /// Dynamic priorities: the job with the earliest absolute deadline runs.
+    EarliestDeadlineFirst,
+    /// Fixed priorities: the task with the shortest period runs.
+    RateMonotonic,
+}
+
+/// The verdict of a schedulability test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedulability {
+    /// The task set is guaranteed to meet all deadlines.
+    Schedulable,
+    /// The test cannot decide; simulate or use an exact analysis.
+    Inconclusive,
+    /// The task set is guaranteed to miss deadlines.
+    Unschedulable,
+}
+
+/// The result of a utilization-bound schedulability test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SchedulabilityTest {
+    /// The total utilization of the task set.
+    pub utilization: f64,
+    /// The utilization bound the policy guarantees.
+    pub bound: f64,
+    /// The verdict.
+    pub verdict: Schedulability,
+}
+
+/// Per-task results of a simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskReport {
+    /// The name of the task.
+    pub name: String,
+    /// The number of jobs released within the horizon.
+    pub jobs_released: usize,
+    /// The number of jobs that ran to completion within the horizon.
+    pub jobs_completed: usize,
+    /// Jobs that finished late, plus unfinished jobs whose deadline passed.
+    pub deadline_misses: usize,
+    /// The longest observed release-to-completion time.
+    pub max_response_time: Duration,
+    /// The mean release-to-completion time of completed jobs.
+    pub mean_response_time: Duration,
+}
+
+/// The results of simulating a task set under one policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationReport {
+    /// The policy that was simulated.
+    pub policy: SchedulingPolicy,
+    /// The simulated time span.
+    pub horizon: Duration,
+    /// The time the processor spent executing jobs.
+    pub busy_time: Duration,
+    /// Results for each task, in task set order.
+    pub tasks: Vec<TaskReport>,
+}
+
+impl SimulationReport {
+    /// Returns the total number of deadline misses across all tasks.
+    pub fn deadline_misses(&self) -> usize {
+        self.tasks.iter().map(|t| t.deadline_misses).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Job {
+    task: usize,
+    release: u64,
+    deadline: u64,
+    remaining: u64,
+}
+
+/// A discrete-event simulator for a set of real-time tasks on one processor.
+///
+/// Jobs always run for their full WCET, so the simulation shows worst-case
+/// behavior for the release pattern it generates. Late jobs keep running until
+/// they finish rather than being aborted.
+pub struct RealTimeSimulator {
+    tasks: Vec<RealTimeTask>,
+    rng: SmallRng,
+}
+
+impl RealTimeSimulator {
+    /// Creates a new `RealTimeSimulator` for the given task set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any task has a zero period.
+    pub fn new(tasks: Vec<RealTimeTask>) -> Self {
+        assert!(
+            tasks.iter().all(|t| !t.period.is_zero()),
+            "every task needs a positive period"
+        );
+        RealTimeSimulator {
+            tasks,
+            rng: SmallRng::from_entropy(),
+        }
+    }
+
+    /// Seeds the sporadic release generator, making simulations reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Returns the tasks being simulated.
+    pub fn tasks(&self) -> &[RealTimeTask] {
+        &self.tasks
+    }
+
+    /// Returns the total utilization of the task set.
+    pub fn utilization(&self) -> f64 {
+        self.tasks.iter().map(RealTimeTask::utilization).sum()
+    }
+
+    /// Returns the least common multiple of the task periods, after which a
+    /// synchronous periodic schedule repeats.
+    ///
+    /// Coprime periods make the hyperperiod grow quickly. One longer than
+    /// `u64::MAX` nanoseconds (about 584 years) saturates to that value.
+    pub fn hyperperiod(&self) -> Duration {
+        let nanos = self
+            .tasks
+            .iter()
+            .map(|t| t.period.as_nanos())
+            .try_fold(1u128, |acc, p| (acc / gcd(acc, p)).checked_mul(p))
+            .and_then(|nanos| u64::try_from(nanos).ok())
+            .unwrap_or(u64::MAX);
+        Duration::from_nanos(nanos)
+    }
+
+    /// Applies the classic utilization-bound test for `policy`.
+    ///
+    /// For EDF the bound is 1.0, which is exact when every deadline equals its
+    /// period; with shorter deadlines the density `Σ wcet / min(deadline, period)`
+    /// is used as a sufficient test. For RM it is the Liu & Layland bound
+    /// `n (2^(1/n) - 1)`, which is sufficient but not necessary.
+    pub fn schedulability(&self, policy: SchedulingPolicy) -> SchedulabilityTest {
+        let utilization = self.utilization();
+        let n = self.tasks.len() as f64;
+        let (bound, load) = match policy {
+            SchedulingPolicy::EarliestDeadlineFirst => {
+                let density: f64 = self
+                    .tasks
+                    .iter()
+                    .map(|t| t.wcet.as_secs_f64() / t.deadline.min(t.period).as_secs_f64())
+                    .sum();
+                (1.0, density)
+            }
+            SchedulingPolicy::RateMonotonic => {
+                let bound = if n == 0.0 {
+                    1.0
+                } else {
+                    n * (2f64.powf(1.0 / n) - 1.0)
+                };
+                (bound, utilization)
+            }
+        };
+        let verdict = if utilization > 1.0 {
+            Schedulability::Unschedulable
+        } else if load <= bound {
+            Schedulability::Schedulable
+        } else {
+            Schedulability::Inconclusive
+        };
+        SchedulabilityTest {
+            utilization,
+            bound,
+            verdict,
+        }
+    }
+
+    /// Simulates the task set under `policy` from time zero to `horizon`.
+    pub fn simulate(&mut self, policy: SchedulingPolicy, horizon: Duration) -> SimulationReport {
+        let horizon_ns = horizon.as_nanos() as u64;
+        let mut next_release: Vec<u64> = self
+            .tasks
+            .iter()
+            .map(|t| t.offset.as_nanos() as u64)
+            .collect();
+        let mut reports: Vec<TaskReport> = self
+            .tasks
+            .iter()
+            .map(|t| TaskReport {
+                name: t.name.clone(),
+                jobs_released: 0,
+                jobs_completed: 0,
+                deadline_misses: 0,
+                max_response_time: Duration::ZERO,
+                mean_response_time: Duration::ZERO,
+            })
+            .collect();
+        let mut total_response = vec![0u64; self.tasks.len()];
+        let mut jobs: Vec<Job> = Vec::new();
+        let mut busy = 0u64;
+        let mut now = 0u64;
+
+        while now < horizon_ns {
+            for (i, task) in self.tasks.iter().enumerate() {
+                while next_release[i] <= now {
+                    let release = next_release[i];
+                    jobs.push(Job {
+                        task: i,
+                        release,
+                        deadline: release + task.deadline.as_nanos() as u64,
+                        remaining: task.wcet.as_nanos() as u64,
+                    });
+                    reports[i].jobs_released += 1;
+                    let extra = match task.arrival {
+                        ArrivalPattern::Periodic => 0,
+                        ArrivalPattern::Sporadic { max_extra_delay } => {
+                            self.rng.gen_range(0..=max_extra_delay.as_nanos() as u64)
+                        }
+                    };
+                    next_release[i] += task.period.as_nanos() as u64 + extra;
+                }
+            }
+
+            let next_event = next_release.iter().copied().min().unwrap_or(u64::MAX);
+            let next_event = next_event.min(horizon_ns);
+            let running = jobs
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, job)| self.priority_key(policy, job))
+                .map(|(idx, _)| idx);
+            let Some(idx) = running else {
+                now = next_event;
+                continue;
+            };
+
+            let slice = jobs[idx].remaining.min(next_event - now);
+            now += slice;
+            busy += slice;
+            jobs[idx].remaining -= slice;
+            if jobs[idx].remaining == 0 {
+                let job = jobs.swap_remove(idx);
+                let report = &mut reports[job.task];
+                let response = now - job.release;
+                report.jobs_completed += 1;
+                report.max_response_time =
+                    report.max_response_time.max(Duration::from_nanos(response));
+                total_response[job.task] += response;
+                if now > job.deadline {
+                    report.deadline_misses += 1;
+                }
+            }
+        }
+
+        for job in &jobs {
+            if job.deadline <= horizon_ns {
+                reports[job.task].deadline_misses += 1;
+            }
+        }
+        for (report, total) in reports.iter_mut().zip(total_response) {
+            if report.jobs_completed > 0 {
+                report.mean_response_time =
+                    Duration::from_nanos(total / report.jobs_completed as u64);
+            }
+        }
+
+        SimulationReport {
+            policy,
+            horizon,
+            busy_time: Duration::from_nanos(busy),
+            tasks: reports,
+        }
+    }
+
+    /// Lower keys run first; ties are broken by release time, then task order.
+    fn priority_key(&self, policy: SchedulingPolicy, job: &Job) -> (u64, u64, usize) {
+        let primary = match policy {
+            SchedulingPolicy::EarliestDeadlineFirst => job.deadline,
+            SchedulingPolicy::RateMonotonic => self.tasks[job.task].period.as_nanos() as u64,
+        };
+        (primary, job.release, job.task)
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_edf_and_rm_scheduling() {
+        let ms = Duration::from_millis;
+        // U = 1/4 + 2/6 + 3/8 ≈ 0.958: above the RM bound for three tasks
+        // (≈ 0.780) but within EDF's bound of 1.
+        let tasks = vec![
+            RealTimeTask::periodic("sensor", ms(1), ms(4)),
+            RealTimeTask::periodic("control", ms(2), ms(6)),
+            RealTimeTask::periodic("logger", ms(3), ms(8)),
+        ];
+        let mut simulator = RealTimeSimulator::new(tasks).with_seed(1);
+        assert_eq!(simulator.hyperperiod(), ms(24));
+        let coprime = RealTimeSimulator::new(
+            [
+                999_999_937,
+                999_999_929,
+                999_999_893,
+                999_999_883,
+                999_999_797,
+            ]
+            .into_iter()
+            .map(|p| RealTimeTask::periodic("poll", ms(1), Duration::from_nanos(p)))
+            .collect(),
+        );
+        assert_eq!(coprime.hyperperiod(), Duration::from_nanos(u64::MAX));
+
+        let edf = simulator.schedulability(SchedulingPolicy::EarliestDeadlineFirst);
+        assert_eq!(edf.verdict, Schedulability::Schedulable);
+        let rm = simulator.schedulability(SchedulingPolicy::RateMonotonic);
+        assert_eq!(rm.verdict, Schedulability::Inconclusive);
+        assert!((rm.bound - 0.7798).abs() < 1e-3);
+
+        let horizon = simulator.hyperperiod() * 10;
+        let edf = simulator.simulate(SchedulingPolicy::EarliestDeadlineFirst, horizon);
+        assert_eq!(
+            edf.deadline_misses(),
+            0,
+            "EDF meets every deadline when U <= 1"
+        );
+        assert_eq!(edf.tasks[0].jobs_released, 60);
+
+        let rm = simulator.simulate(SchedulingPolicy::RateMonotonic, horizon);
+        assert!(
+            rm.tasks[2].deadline_misses > 0,
+            "The lowest-priority task misses under RM"
+        );
+        assert_eq!(
+            rm.tasks[0].max_response_time,
+            ms(1),
+            "The highest-priority task is never preempted"
+        );
+
+        let mut overloaded = RealTimeSimulator::new(vec![
+            RealTimeTask::sporadic("burst", ms(3), ms(4), ms(1)),
+            RealTimeTask::periodic("tick", ms(2), ms(4)),
+        ])
+        .with_seed(7);
+        let test = overloaded.schedulability(SchedulingPolicy::EarliestDeadlineFirst);
+        assert_eq!(test.verdict, Schedulability::Unschedulable);
+        let report = overloaded.simulate(SchedulingPolicy::EarliestDeadlineFirst, ms(100));
+        assert!(report.deadline_misses() > 0);
+        assert!(report.busy_time <= report.horizon);
+    }
+}
@@ -0,0 +1,445 @@
+use super::clock::{Clock, MonotonicClock};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::Hash;
+use std::time::Duration;
+
+/// A weighted directed graph that can be searched by [`AnytimeAStar`].
+///
+/// The graph is explored lazily through `successors`, so it may be implicit
+/// (a grid, a state space) rather than stored in memory.
+pub trait SearchGraph {
+    /// The type of the graph's nodes.
+    type Node: Clone + Eq + Hash;
+
+    /// Returns the nodes reachable from `node` in one step, with the
+    /// non-negative cost of each edge.
+    fn successors(&self, node: &Self::Node) -> Vec<(Self::Node, f64)>;
+
+    /// Estimates the cost of the cheapest path from `node` to `goal`.
+    ///
+    /// The suboptimality bounds reported by [`AnytimeAStar`] only hold if the
+    /// heuristic is consistent: it never overestimates the cost of an edge plus
+    /// the estimate from its target, and is `0.0` at the goal.
+    fn heuristic(&self, node: &Self::Node, goal: &Self::Node) -> f64;
+}
+
+/// The best path found by an [`AnytimeAStar`] search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult<N> {
+    /// The nodes of the path from start to goal, or `None` if no path was
+    /// found before the deadline (or none exists).
+    pub path: Option<Vec<N>>,
+    /// The total edge cost of `path`, or infinity if there is none.
+    pub cost: f64,
+    /// A proven bound on `cost` divided by the optimal cost. `1.0` means the
+    /// path is optimal; infinity means no path has been found yet.
+    pub bound: f64,
+    /// Whether the search proved its answer optimal (or proved that no path
+    /// exists) before the deadline.
+    pub completed: bool,
+    /// The number of solutions published, each better bounded than the last.
+    pub improvements: usize,
+    /// The number of node expansions performed.
+    pub expansions: u64,
+    /// The time spent searching.
+    pub elapsed: Duration,
+}
+
+/// Anytime Repairing A* (Likhachev, Gordon & Thrun, 2003).
+///
+/// The search starts as a weighted A* with an inflated heuristic, which finds
+/// a first path quickly whose cost is at most `initial_epsilon` times the
+/// optimum. While time remains it lowers the inflation factor step by step
+/// and repairs the previous search instead of starting over: only nodes whose
+/// cost improved since they were expanded are revisited. Each finished
+/// iteration publishes a new path together with the tightest bound the open
+/// list proves, so a search interrupted by its deadline still returns the best
+/// path found and an honest statement of how far from optimal it can be.
+pub struct AnytimeAStar<C = MonotonicClock> {
+    deadline: Duration,
+    clock: C,
+    initial_epsilon: f64,
+    epsilon_step: f64,
+}
+
+impl AnytimeAStar {
+    /// Creates a new `AnytimeAStar` with a specified deadline in milliseconds.
+    pub fn new(deadline_ms: u64) -> Self {
+        Self::with_clock(Duration::from_millis(deadline_ms), MonotonicClock::new())
+    }
+}
+
+impl<C: Clock> AnytimeAStar<C> {
+    /// The default inflation factor of the first iteration.
+    pub const DEFAULT_INITIAL_EPSILON: f64 = 3.0;
+    /// The default amount the inflation factor drops between iterations.
+    pub const DEFAULT_EPSILON_STEP: f64 = 0.5;
+
+    /// Creates a new `AnytimeAStar` that measures its deadline with `clock`.
+    pub fn with_clock(deadline: Duration, clock: C) -> Self {
+        AnytimeAStar {
+            deadline,
+            clock,
+            initial_epsilon: Self::DEFAULT_INITIAL_EPSILON,
+            epsilon_step: Self::DEFAULT_EPSILON_STEP,
+        }
+    }
+
+    /// Sets the inflation factor of the first iteration and how much it drops
+    /// after each one. A larger initial factor finds the first path faster.
+    pub fn with_epsilon(mut self, initial: f64, step: f64) -> Self {
+        assert!(initial >= 1.0, "The inflation factor must be at least 1");
+        assert!(step > 0.0, "The inflation factor must decrease");
+        self.initial_epsilon = initial;
+        self.epsilon_step = step;
+        self
+    }
+
+    /// Searches `graph` for a path from `start` to `goal` until the path is
+    /// proven optimal or the deadline passes.
+    pub fn search<G: SearchGraph>(
+        &self,
+        graph: &G,
+        start: G::Node,
+        goal: G::Node,
+    ) -> SearchResult<G::Node> {
+        let start_time = self.clock.now();
+        let mut search = Search::new(graph, start, goal);
+        let mut result = SearchResult {
+            path: None,
+            cost: f64::INFINITY,
+            bound: f64::INFINITY,
+            completed: false,
+            improvements: 0,
+            expansions: 0,
+            elapsed: Duration::ZERO,
+        };
+
+        let mut epsilon = self.initial_epsilon;
+        loop {
+            search.rebuild_open(epsilon);
+            if !search.improve_path(epsilon, || self.time_exceeded(start_time)) {
+                break;
+            }
+            let bound = search.bound(epsilon);
+            result.bound = bound;
+            if let Some(path) = search.path() {
+                result.path = Some(path);
+                result.cost = search.goal_cost();
+                result.improvements += 1;
+            }
+            if bound <= 1.0 || result.path.is_none() {
+                // Either optimal, or the graph was exhausted without reaching
+                // the goal, which no smaller inflation factor can change.
+                result.completed = true;
+                break;
+            }
+            if self.time_exceeded(start_time) {
+                break;
+            }
+            epsilon = (epsilon - self.epsilon_step).max(1.0);
+        }
+
+        result.expansions = search.expansions;
+        result.elapsed = self.clock.now().saturating_sub(start_time);
+        result
+    }
+
+    /// Checks whether the search has run past its deadline.
+    fn time_exceeded(&self, start_time: Duration) -> bool {
+        self.clock.now().saturating_sub(start_time) >= self.deadline
+    }
+}
+
+/// An entry of the open list, ordered so that `BinaryHeap` pops the smallest
+/// key first, breaking ties towards larger `g` (deeper nodes).
+struct OpenEntry<N> {
+    key: f64,
+    g: f64,
+    node: N,
+}
+
+impl<N> PartialEq for OpenEntry<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<N> Eq for OpenEntry<N> {}
+
+impl<N> PartialOrd for OpenEntry<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for OpenEntry<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .total_cmp(&self.key)
+            .then_with(|| self.g.total_cmp(&other.g))
+    }
+}
+
+/// The state ARA* carries from one iteration to the next.
+struct Search<'a, G: SearchGraph> {
+    graph: &'a G,
+    start: G::Node,
+    goal: G::Node,
+    g: HashMap<G::Node, f64>,
+    parent: HashMap<G::Node, G::Node>,
+    /// Members of the open list. The heap may hold stale duplicates, which
+    /// are skipped when popped.
+    open: HashSet<G::Node>,
+    heap: BinaryHeap<OpenEntry<G::Node>>,
+    closed: HashSet<G::Node>,
+    /// Nodes whose cost improved after they were expanded in this iteration.
+    incons: HashSet<G::Node>,
+    expansions: u64,
+}
+
+impl<'a, G: SearchGraph> Search<'a, G> {
+    fn new(graph: &'a G, start: G::Node, goal: G::Node) -> Self {
+        let mut g = HashMap::new();
+        g.insert(start.clone(), 0.0);
+        let mut open = HashSet::new();
+        open.insert(start.clone());
+        Search {
+            graph,
+            start,
+            goal,
+            g,
+            parent: HashMap::new(),
+            open,
+            heap: BinaryHeap::new(),
+            closed: HashSet::new(),
+            incons: HashSet::new(),
+            expansions: 0,
+        }
+    }
+
+    fn cost(&self, node: &G::Node) -> f64 {
+        self.g.get(node).copied().unwrap_or(f64::INFINITY)
+    }
+
+    fn goal_cost(&self) -> f64 {
+        self.cost(&self.goal)
+    }
+
+    fn key(&self, node: &G::Node, g: f64, epsilon: f64) -> f64 {
+        g + epsilon * self.graph.heuristic(node, &self.goal)
+    }
+
+    /// Starts a new iteration: inconsistent nodes rejoin the open list, the
+    /// heap is re-keyed for the new inflation factor and nothing is closed.
+    fn rebuild_open(&mut self, epsilon: f64) {
+        self.open.extend(self.incons.drain());
+        self.closed.clear();
+        let entries: Vec<_> = self
+            .open
+            .iter()
+            .map(|node| {
+                let g = self.cost(node);
+                OpenEntry {
+                    key: self.key(node, g, epsilon),
+                    g,
+                    node: node.clone(),
+                }
+            })
+            .collect();
+        self.heap = BinaryHeap::from(entries);
+    }
+
+    /// Expands nodes until no open node could improve the path to the goal
+    /// under `epsilon`. Returns `false` if `expired` fired first.
+    fn improve_path(&mut self, epsilon: f64, mut expired: impl FnMut() -> bool) -> bool {
+        loop {
+            // Discard stale heap entries before looking at the minimum key.
+            while let Some(top) = self.heap.peek() {
+                if self.open.contains(&top.node) && top.g == self.cost(&top.node) {
+                    break;
+                }
+                self.heap.pop();
+            }
+            let goal_key = self.key(&self.goal, self.goal_cost(), epsilon);
+            match self.heap.peek() {
+                Some(top) if goal_key > top.key => {}
+                _ => return true,
+            }
+            if expired() {
+                return false;
+            }
+
+            let OpenEntry { node, g, .. } = self.heap.pop().expect("peeked above");
+            self.open.remove(&node);
+            self.closed.insert(node.clone());
+            self.expansions += 1;
+
+            for (next, edge_cost) in self.graph.successors(&node) {
+                let candidate = g + edge_cost;
+                if candidate >= self.cost(&next) {
+                    continue;
+                }
+                self.g.insert(next.clone(), candidate);
+                self.parent.insert(next.clone(), node.clone());
+                if self.closed.contains(&next) {
+                    self.incons.insert(next);
+                } else {
+                    self.heap.push(OpenEntry {
+                        key: self.key(&next, candidate, epsilon),
+                        g: candidate,
+                        node: next.clone(),
+                    });
+                    self.open.insert(next);
+                }
+            }
+        }
+    }
+
+    /// The suboptimality bound proven after an iteration with `epsilon`:
+    /// every cheaper path would have to pass through an open or inconsistent
+    /// node, and none of them can reach the goal for less than their
+    /// uninflated `g + h`.
+    fn bound(&self, epsilon: f64) -> f64 {
+        let goal_cost = self.goal_cost();
+        if goal_cost.is_infinite() {
+            return f64::INFINITY;
+        }
+        let lower = self
+            .open
+            .iter()
+            .chain(&self.incons)
+            .map(|node| self.cost(node) + self.graph.heuristic(node, &self.goal))
+            .fold(f64::INFINITY, f64::min);
+        if goal_cost == 0.0 || lower >= goal_cost {
+            return 1.0;
+        }
+        epsilon.min(goal_cost / lower).max(1.0)
+    }
+
+    /// Follows parent pointers back from the goal.
+    fn path(&self) -> Option<Vec<G::Node>> {
+        if self.goal_cost().is_infinite() {
+            return None;
+        }
+        let mut path = vec![self.goal.clone()];
+        let mut node = &self.goal;
+        while *node != self.start {
+            node = self.parent.get(node)?;
+            path.push(node.clone());
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_aware::clock::VirtualClock;
+
+    /// A 4-connected grid with walls. Entering a mud cell costs 5, any other
+    /// cell costs 1.
+    struct Grid {
+        size: i32,
+        walls: HashSet<(i32, i32)>,
+        mud: HashSet<(i32, i32)>,
+    }
+
+    impl SearchGraph for Grid {
+        type Node = (i32, i32);
+
+        fn successors(&self, &(x, y): &(i32, i32)) -> Vec<((i32, i32), f64)> {
+            [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+                .into_iter()
+                .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && nx < self.size && ny < self.size)
+                .filter(|node| !self.walls.contains(node))
+                .map(|node| (node, if self.mud.contains(&node) { 5.0 } else { 1.0 }))
+                .collect()
+        }
+
+        fn heuristic(&self, &(x, y): &(i32, i32), &(gx, gy): &(i32, i32)) -> f64 {
+            ((x - gx).abs() + (y - gy).abs()) as f64
+        }
+    }
+
+    #[test]
+    fn test_verify_anytime_search() {
+        // A band of mud across the straight line from start to goal. The
+        // inflated heuristic wades through it; the optimal path walks around.
+        let mud = (5..25)
+            .flat_map(|x| (10..21).map(move |y| (x, y)))
+            .collect();
+        let grid = Grid {
+            size: 30,
+            walls: HashSet::new(),
+            mud,
+        };
+        let (start, goal) = ((0, 15), (29, 15));
+        let optimal = 29.0 + 2.0 * 6.0;
+
+        let clock = VirtualClock::new(); // never advances, so never expires
+        let search = AnytimeAStar::with_clock(Duration::from_millis(20), &clock);
+        let result = search.search(&grid, start, goal);
+        assert!(result.completed, "Unlimited time should prove optimality");
+        assert_eq!(result.bound, 1.0);
+        assert_eq!(result.cost, optimal);
+        assert!(
+            result.improvements > 1,
+            "The first path should have been improved"
+        );
+        let path = result.path.unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        for pair in path.windows(2) {
+            assert_eq!(
+                grid.heuristic(&pair[0], &pair[1]),
+                1.0,
+                "Steps must be adjacent"
+            );
+        }
+
+        // Interrupted searches return a path whose cost respects the
+        // reported bound, and more time never loosens the bound.
+        let mut previous_bound = f64::INFINITY;
+        for ticks in [60, 120, 200] {
+            let clock = VirtualClock::stepping(Duration::from_micros(1));
+            let search = AnytimeAStar::with_clock(Duration::from_micros(ticks), &clock)
+                .with_epsilon(5.0, 1.0);
+            let result = search.search(&grid, start, goal);
+            assert!(result.path.is_some(), "A first path should come quickly");
+            assert!(result.bound <= 5.0);
+            assert!(result.cost <= result.bound * optimal + 1e-9);
+            assert!(
+                result.bound <= previous_bound,
+                "More time must not loosen the bound"
+            );
+            previous_bound = result.bound;
+            if ticks == 60 {
+                assert!(!result.completed);
+                assert!(
+                    result.cost > optimal,
+                    "The first path wades through the mud"
+                );
+            }
+        }
+        assert_eq!(
+            previous_bound, 1.0,
+            "200 clock ticks suffice to prove optimality"
+        );
+
+        // A wall around the goal makes it unreachable.
+        let walls = [(28, 14), (28, 15), (28, 16), (29, 14), (29, 16)].into();
+        let grid = Grid {
+            size: 30,
+            walls,
+            mud: HashSet::new(),
+        };
+        let result = search.search(&grid, start, goal);
+        assert!(result.completed);
+        assert_eq!(result.path, None);
+        assert_eq!(result.bound, f64::INFINITY);
+    }
+}
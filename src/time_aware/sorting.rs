@@ -0,0 +1,450 @@
+use super::clock::{Clock, MonotonicClock};
+use super::{sortedness, AnytimeQuicksort, AnytimeResult};
+use std::cmp::Ordering;
+use std::time::Duration;
+
+/// A deadline-bounded sort that reports how far it got.
+///
+/// [`AnytimeQuicksort`] is the simplest implementation but degrades to O(n²)
+/// on sorted or otherwise adversarial input. [`AnytimeIntrosort`] and
+/// [`AnytimeMergesort`] guarantee O(n log n) work on every input, so deadlines
+/// sized from `WcetAnalyzer` measurements stay valid when the input is hostile.
+pub trait AnytimeSort {
+    /// Sorts the given array with a comparator function until the deadline is met.
+    fn sort_by<T, F>(&mut self, arr: &mut [T], compare: F) -> AnytimeResult
+    where
+        F: FnMut(&T, &T) -> Ordering;
+
+    /// Sorts the given array until the deadline is met.
+    fn sort<T: Ord>(&mut self, arr: &mut [T]) -> AnytimeResult {
+        self.sort_by(arr, T::cmp)
+    }
+}
+
+impl<C: Clock> AnytimeSort for AnytimeQuicksort<C> {
+    fn sort_by<T, F>(&mut self, arr: &mut [T], compare: F) -> AnytimeResult
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        AnytimeQuicksort::sort_by(self, arr, compare)
+    }
+}
+
+/// The deadline of one sorting run.
+struct Budget<'a, C> {
+    clock: &'a C,
+    start: Duration,
+    deadline: Duration,
+}
+
+impl<'a, C: Clock> Budget<'a, C> {
+    fn start(clock: &'a C, deadline: Duration) -> Self {
+        Budget {
+            clock,
+            start: clock.now(),
+            deadline,
+        }
+    }
+
+    fn exceeded(&self) -> bool {
+        self.elapsed() >= self.deadline
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_sub(self.start)
+    }
+
+    fn finish<T, F>(
+        &self,
+        arr: &[T],
+        completed: bool,
+        finalized: usize,
+        compare: F,
+    ) -> AnytimeResult
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let elapsed = self.elapsed();
+        AnytimeResult {
+            elapsed,
+            completed,
+            finalized_partitions: finalized,
+            sortedness: if completed {
+                1.0
+            } else {
+                sortedness(arr, compare)
+            },
+        }
+    }
+}
+
+/// An anytime introsort: quicksort with a median-of-three pivot that switches
+/// a range to heapsort once its recursion depth exceeds `2 log2 n`, and
+/// finishes short ranges with insertion sort.
+///
+/// The depth limit bounds the total work at O(n log n) whatever the input.
+/// `finalized_partitions` counts completed partitioning passes as well as
+/// ranges finished by heapsort or insertion sort.
+pub struct AnytimeIntrosort<C = MonotonicClock> {
+    deadline: Duration,
+    clock: C,
+}
+
+impl AnytimeIntrosort {
+    /// Creates a new `AnytimeIntrosort` with a specified deadline in milliseconds.
+    pub fn new(deadline_ms: u64) -> Self {
+        Self::with_clock(Duration::from_millis(deadline_ms), MonotonicClock::new())
+    }
+}
+
+impl<C: Clock> AnytimeIntrosort<C> {
+    /// Ranges this short are finished with insertion sort.
+    const INSERTION_SORT_LEN: usize = 16;
+
+    /// Creates a new `AnytimeIntrosort` that measures its deadline with `clock`.
+    pub fn with_clock(deadline: Duration, clock: C) -> Self {
+        AnytimeIntrosort { deadline, clock }
+    }
+}
+
+impl<C: Clock> AnytimeSort for AnytimeIntrosort<C> {
+    fn sort_by<T, F>(&mut self, arr: &mut [T], mut compare: F) -> AnytimeResult
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let budget = Budget::start(&self.clock, self.deadline);
+        let max_depth = 2 * (usize::BITS - arr.len().leading_zeros()) as usize;
+        let mut pending = vec![(0, arr.len(), max_depth)];
+        let mut finalized = 0;
+        let mut completed = true;
+
+        while let Some((lo, hi, depth)) = pending.pop() {
+            if budget.exceeded() {
+                completed = false;
+                break;
+            }
+            let range = &mut arr[lo..hi];
+            if range.len() <= 1 {
+                continue;
+            }
+            let finished = if range.len() <= Self::INSERTION_SORT_LEN {
+                insertion_sort(range, &mut compare, &budget)
+            } else if depth == 0 {
+                heapsort(range, &mut compare, &budget)
+            } else {
+                move_median_of_three_to_end(range, &mut compare);
+                match lomuto_partition(range, &mut compare, &budget) {
+                    Some(p) => {
+                        pending.push((lo, lo + p, depth - 1));
+                        pending.push((lo + p + 1, hi, depth - 1));
+                        true
+                    }
+                    None => false,
+                }
+            };
+            if !finished {
+                completed = false;
+                break;
+            }
+            finalized += 1;
+        }
+
+        budget.finish(arr, completed, finalized, compare)
+    }
+}
+
+/// An anytime bottom-up mergesort: sorted runs of width 1, 2, 4, ... are
+/// merged pass by pass, so every run of the current width is sorted at any
+/// point the deadline can fire.
+///
+/// Every pass costs O(n), giving O(n log n) work on every input. Merges are
+/// computed on element indices and applied as an in-place permutation, so `T`
+/// need not be `Clone`. A merge cut off by the deadline is discarded.
+/// `finalized_partitions` counts completed merges.
+pub struct AnytimeMergesort<C = MonotonicClock> {
+    deadline: Duration,
+    clock: C,
+}
+
+impl AnytimeMergesort {
+    /// Creates a new `AnytimeMergesort` with a specified deadline in milliseconds.
+    pub fn new(deadline_ms: u64) -> Self {
+        Self::with_clock(Duration::from_millis(deadline_ms), MonotonicClock::new())
+    }
+}
+
+impl<C: Clock> AnytimeMergesort<C> {
+    /// Creates a new `AnytimeMergesort` that measures its deadline with `clock`.
+    pub fn with_clock(deadline: Duration, clock: C) -> Self {
+        AnytimeMergesort { deadline, clock }
+    }
+}
+
+impl<C: Clock> AnytimeSort for AnytimeMergesort<C> {
+    fn sort_by<T, F>(&mut self, arr: &mut [T], mut compare: F) -> AnytimeResult
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let budget = Budget::start(&self.clock, self.deadline);
+        let n = arr.len();
+        let mut order: Vec<usize> = Vec::with_capacity(n);
+        let mut visited = vec![false; n];
+        let mut finalized = 0;
+        let mut completed = true;
+        let mut width = 1;
+
+        'passes: while width < n {
+            for lo in (0..n).step_by(2 * width) {
+                let mid = (lo + width).min(n);
+                let hi = (lo + 2 * width).min(n);
+                if mid == hi {
+                    continue;
+                }
+                if !merge_order(arr, lo, mid, hi, &mut order, &mut compare, &budget) {
+                    completed = false;
+                    break 'passes;
+                }
+                apply_order(arr, lo, &order, &mut visited);
+                finalized += 1;
+            }
+            width *= 2;
+        }
+
+        budget.finish(arr, completed, finalized, compare)
+    }
+}
+
+fn insertion_sort<T, F, C>(arr: &mut [T], compare: &mut F, budget: &Budget<'_, C>) -> bool
+where
+    F: FnMut(&T, &T) -> Ordering,
+    C: Clock,
+{
+    for i in 1..arr.len() {
+        if budget.exceeded() {
+            return false;
+        }
+        let mut j = i;
+        while j > 0 && compare(&arr[j], &arr[j - 1]) == Ordering::Less {
+            arr.swap(j, j - 1);
+            j -= 1;
+        }
+    }
+    true
+}
+
+fn heapsort<T, F, C>(arr: &mut [T], compare: &mut F, budget: &Budget<'_, C>) -> bool
+where
+    F: FnMut(&T, &T) -> Ordering,
+    C: Clock,
+{
+    let n = arr.len();
+    for root in (0..n / 2).rev() {
+        if !sift_down(arr, root, n, compare, budget) {
+            return false;
+        }
+    }
+    for end in (1..n).rev() {
+        arr.swap(0, end);
+        if !sift_down(arr, 0, end, compare, budget) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Restores the max-heap property below `root` within `arr[..end]`.
+fn sift_down<T, F, C>(
+    arr: &mut [T],
+    mut root: usize,
+    end: usize,
+    compare: &mut F,
+    budget: &Budget<'_, C>,
+) -> bool
+where
+    F: FnMut(&T, &T) -> Ordering,
+    C: Clock,
+{
+    loop {
+        if budget.exceeded() {
+            return false;
+        }
+        let mut child = 2 * root + 1;
+        if child >= end {
+            return true;
+        }
+        if child + 1 < end && compare(&arr[child], &arr[child + 1]) == Ordering::Less {
+            child += 1;
+        }
+        if compare(&arr[root], &arr[child]) != Ordering::Less {
+            return true;
+        }
+        arr.swap(root, child);
+        root = child;
+    }
+}
+
+/// Moves the median of the first, middle and last elements into the last
+/// slot, where the partition expects its pivot.
+fn move_median_of_three_to_end<T, F>(arr: &mut [T], compare: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let (a, b, c) = (0, arr.len() / 2, arr.len() - 1);
+    let less = |compare: &mut F, x: usize, y: usize| compare(&arr[x], &arr[y]) == Ordering::Less;
+    let median = if less(compare, a, b) {
+        if less(compare, b, c) {
+            b
+        } else if less(compare, a, c) {
+            c
+        } else {
+            a
+        }
+    } else if less(compare, a, c) {
+        a
+    } else if less(compare, b, c) {
+        c
+    } else {
+        b
+    };
+    arr.swap(median, c);
+}
+
+/// Lomuto partition of `arr` around its last element. Returns the pivot's
+/// final index, or `None` if the deadline fired mid-pass.
+fn lomuto_partition<T, F, C>(
+    arr: &mut [T],
+    compare: &mut F,
+    budget: &Budget<'_, C>,
+) -> Option<usize>
+where
+    F: FnMut(&T, &T) -> Ordering,
+    C: Clock,
+{
+    let pivot = arr.len() - 1;
+    let mut i = 0;
+    for j in 0..pivot {
+        if budget.exceeded() {
+            return None;
+        }
+        if compare(&arr[j], &arr[pivot]) == Ordering::Less {
+            arr.swap(i, j);
+            i += 1;
+        }
+    }
+    arr.swap(i, pivot);
+    Some(i)
+}
+
+/// Computes the stable merge of the sorted runs `arr[lo..mid]` and
+/// `arr[mid..hi]` as a list of source indices in `order`.
+fn merge_order<T, F, C>(
+    arr: &[T],
+    lo: usize,
+    mid: usize,
+    hi: usize,
+    order: &mut Vec<usize>,
+    compare: &mut F,
+    budget: &Budget<'_, C>,
+) -> bool
+where
+    F: FnMut(&T, &T) -> Ordering,
+    C: Clock,
+{
+    order.clear();
+    let (mut l, mut r) = (lo, mid);
+    while l < mid && r < hi {
+        if budget.exceeded() {
+            return false;
+        }
+        if compare(&arr[r], &arr[l]) == Ordering::Less {
+            order.push(r);
+            r += 1;
+        } else {
+            order.push(l);
+            l += 1;
+        }
+    }
+    order.extend(l..mid);
+    order.extend(r..hi);
+    true
+}
+
+/// Rearranges `arr[lo..lo + order.len()]` so that position `lo + k` receives
+/// the element previously at `order[k]`, by following permutation cycles.
+fn apply_order<T>(arr: &mut [T], lo: usize, order: &[usize], visited: &mut [bool]) {
+    let visited = &mut visited[lo..lo + order.len()];
+    visited.fill(false);
+    for start in 0..order.len() {
+        if visited[start] {
+            continue;
+        }
+        let mut k = start;
+        loop {
+            visited[k] = true;
+            let next = order[k] - lo;
+            if next == start {
+                break;
+            }
+            arr.swap(lo + k, lo + next);
+            k = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_aware::clock::VirtualClock;
+    use crate::time_aware::wcet::InputPattern;
+    use std::sync::Arc;
+
+    /// Sorts `input` with one clock read per unit of work and an n log n budget.
+    fn sort_with_op_budget<S, B>(make: B, input: &[i32]) -> (Vec<i32>, AnytimeResult)
+    where
+        S: AnytimeSort,
+        B: Fn(Duration, Arc<VirtualClock>) -> S,
+    {
+        let n = input.len() as f64;
+        let budget = Duration::from_micros((8.0 * n * n.log2()) as u64);
+        let clock = Arc::new(VirtualClock::stepping(Duration::from_micros(1)));
+        let mut sorter = make(budget, clock);
+        let mut arr = input.to_vec();
+        let result = sorter.sort(&mut arr);
+        (arr, result)
+    }
+
+    #[test]
+    fn test_verify_bounded_worst_case_sorts() {
+        for pattern in InputPattern::ALL {
+            let input = pattern.generate(2_000);
+            let mut expected = input.clone();
+            expected.sort();
+
+            let (arr, result) = sort_with_op_budget(AnytimeIntrosort::with_clock, &input);
+            assert!(
+                result.completed,
+                "Introsort should finish {pattern:?} within n log n work"
+            );
+            assert_eq!(arr, expected);
+
+            let (arr, result) = sort_with_op_budget(AnytimeMergesort::with_clock, &input);
+            assert!(
+                result.completed,
+                "Mergesort should finish {pattern:?} within n log n work"
+            );
+            assert_eq!(arr, expected);
+        }
+
+        let sorted = InputPattern::Sorted.generate(2_000);
+        let (_, result) = sort_with_op_budget(AnytimeQuicksort::with_clock, &sorted);
+        assert!(
+            !result.completed,
+            "Lomuto quicksort is quadratic on sorted input"
+        );
+
+        let mut words = vec!["delta", "alpha", "charlie", "bravo"];
+        let result = AnytimeMergesort::new(1_000).sort_by(&mut words, |a, b| b.cmp(a));
+        assert!(result.completed);
+        assert_eq!(words, vec!["delta", "charlie", "bravo", "alpha"]);
+    }
+}
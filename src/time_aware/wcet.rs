@@ -0,0 +1,607 @@
+use super::clock::{Clock, MonotonicClock};
+use rand::Rng;
+use statrs::consts::EULER_MASCHERONI;
+use statrs::function::gamma::gamma;
+use std::collections::BTreeMap;
+use std::f64::consts::LN_2;
+use std::time::Duration;
+
+/// Summary statistics over a set of execution time samples, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WcetSummary {
+    /// The number of samples.
+    pub count: usize,
+    /// The sample mean.
+    pub mean: f64,
+    /// The sample standard deviation.
+    pub std_dev: f64,
+    /// The largest observed sample (the high-water mark).
+    pub max: f64,
+    /// The 99th percentile.
+    pub p99: f64,
+    /// The 99.9th percentile.
+    pub p999: f64,
+}
+
+/// The extreme value distribution fitted to block maxima.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvtModel {
+    /// The Gumbel distribution, appropriate for light (exponential-like) tails.
+    Gumbel,
+    /// The generalized extreme value distribution, which also estimates a
+    /// shape parameter and so can model bounded or heavy tails.
+    Gev,
+}
+
+/// An extreme value distribution fitted to the maxima of fixed-size blocks of
+/// samples.
+///
+/// The shape follows Hosking's sign convention: `shape > 0` is a bounded
+/// (Weibull-type) tail, `shape < 0` a heavy (Fréchet-type) tail, and `0.0` is
+/// Gumbel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvtFit {
+    /// The model that was fitted.
+    pub model: EvtModel,
+    /// The location parameter, in milliseconds.
+    pub location: f64,
+    /// The scale parameter, in milliseconds.
+    pub scale: f64,
+    /// The shape parameter (always `0.0` for Gumbel).
+    pub shape: f64,
+    /// The number of samples per block.
+    pub block_size: usize,
+}
+
+impl EvtFit {
+    /// Returns the execution time that a single run exceeds with probability
+    /// `exceedance_probability`, e.g. `1e-9`.
+    pub fn pwcet(&self, exceedance_probability: f64) -> f64 {
+        // A block maximum stays below x iff all `block_size` runs do, so the
+        // per-run probability p maps to F_block(x) = (1 - p)^block_size.
+        // `y = -ln F_block(x)` is computed directly to keep tiny p precise.
+        let y = -(self.block_size as f64) * (-exceedance_probability).ln_1p();
+        if self.shape == 0.0 {
+            self.location - self.scale * y.ln()
+        } else {
+            self.location + self.scale / self.shape * (1.0 - y.powf(self.shape))
+        }
+    }
+
+    /// Returns the probability that a single run takes longer than `time_ms`.
+    pub fn exceedance_probability(&self, time_ms: f64) -> f64 {
+        let z = (time_ms - self.location) / self.scale;
+        let y = if self.shape == 0.0 {
+            (-z).exp()
+        } else {
+            let t = 1.0 - self.shape * z;
+            if t <= 0.0 {
+                // Beyond the end of a bounded tail (or below a heavy tail's start).
+                return if self.shape > 0.0 { 0.0 } else { 1.0 };
+            }
+            t.powf(1.0 / self.shape)
+        };
+        // Invert y = -block_size * ln(1 - p).
+        -(-y / self.block_size as f64).exp_m1()
+    }
+}
+
+/// A family of inputs for exercising best, average and adversarial cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputPattern {
+    /// Uniformly random values.
+    Random,
+    /// Ascending values.
+    Sorted,
+    /// Descending values.
+    ReverseSorted,
+    /// Ascending to the middle, then descending.
+    OrganPipe,
+    /// Every value identical.
+    AllEqual,
+}
+
+impl InputPattern {
+    /// Every pattern, for sweeping all of them.
+    pub const ALL: [InputPattern; 5] = [
+        InputPattern::Random,
+        InputPattern::Sorted,
+        InputPattern::ReverseSorted,
+        InputPattern::OrganPipe,
+        InputPattern::AllEqual,
+    ];
+
+    /// Generates an input of `n` integers following this pattern.
+    pub fn generate(&self, n: usize) -> Vec<i32> {
+        match self {
+            InputPattern::Random => {
+                let mut rng = rand::thread_rng();
+                (0..n).map(|_| rng.gen()).collect()
+            }
+            InputPattern::Sorted => (0..n as i32).collect(),
+            InputPattern::ReverseSorted => (0..n as i32).rev().collect(),
+            InputPattern::OrganPipe => (0..n).map(|i| i.min(n - 1 - i) as i32).collect(),
+            InputPattern::AllEqual => vec![0; n],
+        }
+    }
+}
+
+/// How samples far above or below the bulk of a measurement are treated.
+///
+/// For WCET purposes a slow outlier is often exactly the run of interest, so
+/// discarding should only be used when the outliers are known to come from the
+/// measurement environment (preemption, page faults) rather than the code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierPolicy {
+    /// Keep every sample.
+    Keep,
+    /// Discard samples outside Tukey's fences, `[q1 - k * iqr, q3 + k * iqr]`.
+    /// `k = 1.5` is conventional; `k = 3.0` removes only extreme outliers.
+    TukeyFences(f64),
+}
+
+impl OutlierPolicy {
+    /// Applies the policy to `samples` in place, returning the number discarded.
+    pub fn apply(&self, samples: &mut Vec<f64>) -> usize {
+        let k = match *self {
+            OutlierPolicy::Keep => return 0,
+            OutlierPolicy::TukeyFences(k) => k,
+        };
+        if samples.len() < 4 {
+            return 0;
+        }
+        let mut sorted = samples.clone();
+        sorted.sort_by(f64::total_cmp);
+        let (q1, q3) = (percentile(&sorted, 0.25), percentile(&sorted, 0.75));
+        let (low, high) = (q1 - k * (q3 - q1), q3 + k * (q3 - q1));
+        let before = samples.len();
+        samples.retain(|&x| (low..=high).contains(&x));
+        before - samples.len()
+    }
+}
+
+/// Controls an input-parametrized measurement run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasurementPlan {
+    /// The input sizes to measure.
+    pub sizes: Vec<usize>,
+    /// Untimed runs per size before recording starts.
+    pub warmup_iterations: u32,
+    /// Timed runs per size.
+    pub iterations: u32,
+    /// How outlying samples are treated.
+    pub outliers: OutlierPolicy,
+}
+
+/// A growth rate that execution time may follow as input size increases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComplexityClass {
+    /// O(1).
+    Constant,
+    /// O(log n).
+    Logarithmic,
+    /// O(n).
+    Linear,
+    /// O(n log n).
+    Linearithmic,
+    /// O(n²).
+    Quadratic,
+    /// O(n³).
+    Cubic,
+}
+
+impl ComplexityClass {
+    /// Every class, from slowest to fastest growing.
+    pub const ALL: [ComplexityClass; 6] = [
+        ComplexityClass::Constant,
+        ComplexityClass::Logarithmic,
+        ComplexityClass::Linear,
+        ComplexityClass::Linearithmic,
+        ComplexityClass::Quadratic,
+        ComplexityClass::Cubic,
+    ];
+
+    /// Evaluates the growth function at `n`.
+    pub fn eval(&self, n: f64) -> f64 {
+        let n = n.max(1.0);
+        match self {
+            ComplexityClass::Constant => 1.0,
+            ComplexityClass::Logarithmic => n.log2(),
+            ComplexityClass::Linear => n,
+            ComplexityClass::Linearithmic => n * n.log2(),
+            ComplexityClass::Quadratic => n * n,
+            ComplexityClass::Cubic => n * n * n,
+        }
+    }
+}
+
+/// A fitted time model `t(n) = intercept + coefficient * class(n)`, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComplexityFit {
+    /// The growth rate that fits best.
+    pub class: ComplexityClass,
+    /// The fixed cost, in milliseconds.
+    pub intercept: f64,
+    /// The cost per unit of growth, in milliseconds.
+    pub coefficient: f64,
+    /// The sum of squared residuals of the fit.
+    pub residual: f64,
+}
+
+impl ComplexityFit {
+    /// Predicts the execution time, in milliseconds, for input size `n`.
+    pub fn predict(&self, n: usize) -> f64 {
+        self.intercept + self.coefficient * self.class.eval(n as f64)
+    }
+
+    /// Ordinary least squares of `points` against `class`. Fits whose time
+    /// would shrink as `n` grows are rejected.
+    fn least_squares(class: ComplexityClass, points: &[(f64, f64)]) -> Option<ComplexityFit> {
+        let m = points.len() as f64;
+        let xs: Vec<f64> = points.iter().map(|&(n, _)| class.eval(n)).collect();
+        let x_mean = xs.iter().sum::<f64>() / m;
+        let y_mean = points.iter().map(|&(_, t)| t).sum::<f64>() / m;
+        let sxx: f64 = xs.iter().map(|x| (x - x_mean).powi(2)).sum();
+        let (intercept, coefficient) = if sxx == 0.0 {
+            (y_mean, 0.0)
+        } else {
+            let sxy: f64 = xs
+                .iter()
+                .zip(points)
+                .map(|(x, &(_, t))| (x - x_mean) * (t - y_mean))
+                .sum();
+            let coefficient = sxy / sxx;
+            (y_mean - coefficient * x_mean, coefficient)
+        };
+        if coefficient < 0.0 {
+            return None;
+        }
+        let residual = xs
+            .iter()
+            .zip(points)
+            .map(|(x, &(_, t))| (t - intercept - coefficient * x).powi(2))
+            .sum();
+        Some(ComplexityFit {
+            class,
+            intercept,
+            coefficient,
+            residual,
+        })
+    }
+}
+
+/// A tool for analyzing the Worst-Case Execution Time (WCET) of a given function.
+///
+/// Besides summary statistics, it estimates a probabilistic WCET (pWCET) using
+/// extreme value theory: samples are grouped into blocks, the block maxima are
+/// fitted with a Gumbel or GEV distribution by probability-weighted moments,
+/// and the fitted tail answers "which time is exceeded with probability p".
+/// The estimate assumes the samples are independent and identically
+/// distributed, which is only as true as the measurement setup makes it.
+///
+/// Measurements are taken with a [`Clock`], the real monotonic clock by default.
+pub struct WcetAnalyzer<C = MonotonicClock> {
+    /// A collection of execution time samples in milliseconds.
+    pub samples: Vec<f64>,
+    /// Execution time samples in milliseconds, keyed by input size, as
+    /// recorded by [`WcetAnalyzer::measure_inputs`].
+    pub samples_by_size: BTreeMap<usize, Vec<f64>>,
+    clock: C,
+}
+
+impl Default for WcetAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WcetAnalyzer {
+    /// Creates a new `WcetAnalyzer`.
+    pub fn new() -> Self {
+        Self::with_clock(MonotonicClock::new())
+    }
+}
+
+impl<C: Clock> WcetAnalyzer<C> {
+    /// The minimum number of block maxima required for a fit.
+    pub const MIN_BLOCKS: usize = 10;
+
+    /// Creates a new `WcetAnalyzer` that times executions with `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        WcetAnalyzer {
+            samples: Vec::new(),
+            samples_by_size: BTreeMap::new(),
+            clock,
+        }
+    }
+
+    /// Measures the execution time of a function over a specified number of iterations.
+    pub fn measure<F>(&mut self, mut f: F, iterations: u32)
+    where
+        F: FnMut(),
+    {
+        self.samples.clear();
+        for _ in 0..iterations {
+            let start = self.clock.now();
+            f();
+            self.samples.push(self.elapsed_ms(start));
+        }
+    }
+
+    /// Measures `f` on generated inputs of each size in `plan`, recording the
+    /// samples per size in `samples_by_size`.
+    ///
+    /// For every size, `plan.warmup_iterations` untimed runs are made first to
+    /// settle caches, branch predictors and allocator state. Input generation
+    /// is never timed. Outliers are then handled according to `plan.outliers`.
+    pub fn measure_inputs<I, G, F>(&mut self, plan: &MeasurementPlan, mut generate: G, mut f: F)
+    where
+        G: FnMut(usize) -> I,
+        F: FnMut(I),
+    {
+        self.samples_by_size.clear();
+        for &n in &plan.sizes {
+            for _ in 0..plan.warmup_iterations {
+                f(generate(n));
+            }
+            let mut samples = Vec::with_capacity(plan.iterations as usize);
+            for _ in 0..plan.iterations {
+                let input = generate(n);
+                let start = self.clock.now();
+                f(input);
+                samples.push(self.elapsed_ms(start));
+            }
+            plan.outliers.apply(&mut samples);
+            self.samples_by_size.insert(n, samples);
+        }
+    }
+
+    /// Fits each [`ComplexityClass`] to the worst observed time per input size
+    /// in `samples_by_size` and returns the best fit, which can predict the
+    /// execution time for sizes that were never measured.
+    ///
+    /// Returns `None` if fewer than three sizes have samples.
+    pub fn fit_complexity(&self) -> Option<ComplexityFit> {
+        let points: Vec<(f64, f64)> = self
+            .samples_by_size
+            .iter()
+            .filter(|(_, samples)| !samples.is_empty())
+            .map(|(&n, samples)| (n as f64, samples.iter().copied().fold(f64::MIN, f64::max)))
+            .collect();
+        if points.len() < 3 {
+            return None;
+        }
+        ComplexityClass::ALL
+            .iter()
+            .filter_map(|&class| ComplexityFit::least_squares(class, &points))
+            .min_by(|a, b| a.residual.total_cmp(&b.residual))
+    }
+
+    fn elapsed_ms(&self, start: Duration) -> f64 {
+        self.clock.now().saturating_sub(start).as_secs_f64() * 1000.0
+    }
+
+    /// Returns summary statistics of the samples, or `None` if there are none.
+    pub fn summary(&self) -> Option<WcetSummary> {
+        summarize(&self.samples)
+    }
+
+    /// Returns summary statistics of the samples recorded for input size `n`
+    /// by [`Self::measure_inputs`].
+    pub fn size_summary(&self, n: usize) -> Option<WcetSummary> {
+        self.samples_by_size
+            .get(&n)
+            .and_then(|samples| summarize(samples))
+    }
+
+    /// Returns a block size giving roughly 30 blocks, a common compromise
+    /// between per-block convergence to the extreme value limit and the number
+    /// of maxima available for the fit.
+    pub fn default_block_size(&self) -> usize {
+        (self.samples.len() / 30).max(1)
+    }
+
+    /// Fits `model` to the maxima of consecutive blocks of `block_size` samples.
+    /// Trailing samples that do not fill a block are ignored.
+    ///
+    /// Returns `None` if there are fewer than [`Self::MIN_BLOCKS`] blocks or the
+    /// maxima have no spread to fit.
+    pub fn fit_evt(&self, model: EvtModel, block_size: usize) -> Option<EvtFit> {
+        if block_size == 0 {
+            return None;
+        }
+        let mut maxima: Vec<f64> = self
+            .samples
+            .chunks_exact(block_size)
+            .map(|block| block.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+            .collect();
+        if maxima.len() < Self::MIN_BLOCKS {
+            return None;
+        }
+        maxima.sort_by(f64::total_cmp);
+        let (b0, b1, b2) = probability_weighted_moments(&maxima);
+        let l2 = 2.0 * b1 - b0;
+        if l2 <= 0.0 {
+            return None;
+        }
+
+        let (location, scale, shape) = match model {
+            EvtModel::Gumbel => {
+                let scale = l2 / LN_2;
+                (b0 - EULER_MASCHERONI * scale, scale, 0.0)
+            }
+            EvtModel::Gev => {
+                // Hosking, Wallis & Wood (1985) approximation for the shape.
+                let c = l2 / (3.0 * b2 - b0) - LN_2 / 3.0_f64.ln();
+                let shape = 7.8590 * c + 2.9554 * c * c;
+                if shape.abs() < 1e-6 {
+                    let scale = l2 / LN_2;
+                    (b0 - EULER_MASCHERONI * scale, scale, 0.0)
+                } else {
+                    let g = gamma(1.0 + shape);
+                    let scale = l2 * shape / (g * (1.0 - 2.0_f64.powf(-shape)));
+                    (b0 + scale * (g - 1.0) / shape, scale, shape)
+                }
+            }
+        };
+        if !(scale > 0.0 && location.is_finite()) {
+            return None;
+        }
+        Some(EvtFit {
+            model,
+            location,
+            scale,
+            shape,
+            block_size,
+        })
+    }
+
+    /// Returns the execution time, in milliseconds, that a single run exceeds
+    /// with probability `exceedance_probability`, using a Gumbel fit with the
+    /// default block size.
+    pub fn pwcet(&self, exceedance_probability: f64) -> Option<f64> {
+        self.fit_evt(EvtModel::Gumbel, self.default_block_size())
+            .map(|fit| fit.pwcet(exceedance_probability))
+    }
+}
+
+fn summarize(samples: &[f64]) -> Option<WcetSummary> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let count = sorted.len();
+    let mean = sorted.iter().sum::<f64>() / count as f64;
+    let std_dev = if count > 1 {
+        let ss: f64 = sorted.iter().map(|x| (x - mean).powi(2)).sum();
+        (ss / (count - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    Some(WcetSummary {
+        count,
+        mean,
+        std_dev,
+        max: sorted[count - 1],
+        p99: percentile(&sorted, 0.99),
+        p999: percentile(&sorted, 0.999),
+    })
+}
+
+/// Nearest-rank percentile of an ascending slice.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Unbiased estimators of the first three probability-weighted moments of an
+/// ascending sample.
+fn probability_weighted_moments(sorted: &[f64]) -> (f64, f64, f64) {
+    let n = sorted.len() as f64;
+    let (mut b0, mut b1, mut b2) = (0.0, 0.0, 0.0);
+    for (i, &x) in sorted.iter().enumerate() {
+        let i = i as f64;
+        b0 += x;
+        b1 += x * i / (n - 1.0);
+        b2 += x * i * (i - 1.0) / ((n - 1.0) * (n - 2.0));
+    }
+    (b0 / n, b1 / n, b2 / n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_verify_pwcet_estimation() {
+        // Exponentially distributed run times with mean 1ms: a single run
+        // exceeds t with probability exp(-t), so the true pWCET at 1e-9 is
+        // -ln(1e-9) ≈ 20.7ms.
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut analyzer = WcetAnalyzer::new();
+        analyzer.samples = (0..30_000)
+            .map(|_| -(1.0 - rng.gen::<f64>()).ln())
+            .collect();
+
+        let summary = analyzer.summary().unwrap();
+        assert_eq!(summary.count, 30_000);
+        assert!(
+            (summary.mean - 1.0).abs() < 0.05,
+            "Exponential mean should be ~1"
+        );
+        assert!(
+            (summary.std_dev - 1.0).abs() < 0.05,
+            "Exponential std dev should be ~1"
+        );
+        assert!((summary.p99 - 100f64.ln()).abs() < 0.3);
+        assert!(summary.p99 <= summary.p999 && summary.p999 <= summary.max);
+
+        let truth = -(1e-9f64).ln();
+        let pwcet = analyzer.pwcet(1e-9).unwrap();
+        assert!(
+            (pwcet - truth).abs() / truth < 0.15,
+            "Gumbel pWCET {pwcet} should be near {truth}"
+        );
+
+        let gev = analyzer.fit_evt(EvtModel::Gev, 1_000).unwrap();
+        assert!(
+            gev.shape.abs() < 0.25,
+            "An exponential tail should fit a near-zero GEV shape"
+        );
+        let p = gev.exceedance_probability(gev.pwcet(1e-6));
+        assert!(
+            (p - 1e-6).abs() / 1e-6 < 1e-6,
+            "pwcet and exceedance_probability should be inverses"
+        );
+
+        assert!(
+            WcetAnalyzer::new().pwcet(1e-9).is_none(),
+            "No samples means no estimate"
+        );
+    }
+
+    #[test]
+    fn test_verify_input_parametrized_measurement() {
+        let plan = MeasurementPlan {
+            sizes: vec![10, 100],
+            warmup_iterations: 3,
+            iterations: 20,
+            outliers: OutlierPolicy::Keep,
+        };
+        let mut runs = 0;
+        let mut analyzer = WcetAnalyzer::new();
+        analyzer.measure_inputs(
+            &plan,
+            |n| InputPattern::OrganPipe.generate(n),
+            |mut input| {
+                runs += 1;
+                input.sort();
+            },
+        );
+        assert_eq!(
+            runs,
+            2 * (3 + 20),
+            "Warm-up runs should happen but not be recorded"
+        );
+        assert_eq!(analyzer.size_summary(100).unwrap().count, 20);
+        assert!(analyzer.size_summary(50).is_none());
+        assert_eq!(InputPattern::OrganPipe.generate(5), vec![0, 1, 2, 1, 0]);
+
+        let mut samples = vec![1.0, 1.1, 0.9, 1.0, 1.05, 0.95, 40.0];
+        assert_eq!(OutlierPolicy::TukeyFences(3.0).apply(&mut samples), 1);
+        assert!(samples.iter().all(|&t| t < 2.0));
+
+        // Synthetic n log n timings with a fixed overhead.
+        analyzer.samples_by_size = [1_000usize, 2_000, 4_000, 8_000, 16_000]
+            .iter()
+            .map(|&n| (n, vec![0.5 + 1e-4 * n as f64 * (n as f64).log2()]))
+            .collect();
+        let fit = analyzer.fit_complexity().unwrap();
+        assert_eq!(fit.class, ComplexityClass::Linearithmic);
+        let expected = 0.5 + 1e-4 * 1e6 * 1e6f64.log2();
+        assert!((fit.predict(1_000_000) - expected).abs() / expected < 1e-6);
+    }
+}